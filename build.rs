@@ -1,4 +1,7 @@
 fn main() {
-    println!("cargo:rustc-link-lib=framework=IOKit");
-    println!("cargo:rustc-link-lib=dylib=IOReport");
+    // IOKit and IOReport only exist on macOS
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("macos") {
+        println!("cargo:rustc-link-lib=framework=IOKit");
+        println!("cargo:rustc-link-lib=dylib=IOReport");
+    }
 }
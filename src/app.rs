@@ -1,21 +1,64 @@
 use crate::metrics::Metrics;
+use crate::recording::{Frame, Player};
+use crate::output::local_clock;
 use crate::soc::SocInfo;
-use crate::widgets::{cpu_panel, gpu_panel, header, mem_panel, power_bar, History};
+use crate::sources::network::NetworkInterface;
+use crate::sources::thermal_pressure::PressureChange;
+use crate::widgets::process_table::{self, SortBy};
+use crate::widgets::network_panel::{self, Selection};
+use crate::widgets::{
+    battery_panel, cpu_panel, disk_panel, gpu_panel, header, mem_panel, power_bar, thermal_panel, History,
+};
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders};
+use std::collections::{HashMap, VecDeque};
 use std::io;
 use std::sync::mpsc;
 use std::time::Duration;
 
 const HISTORY_CAP: usize = 120;
 
+/// Thermal pressure changes kept for the thermal panel.
+const PRESSURE_LOG_CAP: usize = 8;
+
+/// Seek step for replay, in recording time.
+const SEEK_STEP_MS: i64 = 10_000;
+
+/// Where the app gets its samples from.
+pub enum Feed {
+    /// Live samples from the sampler thread.
+    Live(mpsc::Receiver<Metrics>),
+    /// Samples played back from a recording.
+    Replay(Player),
+}
+
 pub struct App {
     soc: SocInfo,
     current: Metrics,
+    /// Whether `current` is a real sample rather than the empty default,
+    /// which no pressure change should be logged against.
+    has_sample: bool,
     cpu_history: History,
     gpu_history: History,
     mem_history: History,
     power_history: History,
+    thermal_history: History,
+    battery_history: History,
+    /// Battery current over the history window, for the time estimate.
+    battery_amps: VecDeque<f64>,
+    /// Combined throughput of all disks.
+    disk_history: History,
+    /// RX and TX rate history per interface name.
+    net_history: HashMap<String, (History, History)>,
+    /// The interface picked with `i`; the busiest one when unset.
+    net_selected: Option<String>,
+    /// Include loopback and inactive interfaces.
+    net_show_all: bool,
+    process_sort: SortBy,
+    /// Show the GPU P-state histogram instead of the utilization graph.
+    gpu_histogram: bool,
+    /// Thermal pressure changes, newest last.
+    pressure_log: VecDeque<PressureChange>,
     interval: Duration,
 }
 
@@ -24,10 +67,21 @@ impl App {
         Self {
             soc,
             current: Metrics::default(),
+            has_sample: false,
             cpu_history: History::new(HISTORY_CAP),
             gpu_history: History::new(HISTORY_CAP),
             mem_history: History::new(HISTORY_CAP),
             power_history: History::new(HISTORY_CAP),
+            thermal_history: History::new(HISTORY_CAP),
+            battery_history: History::new(HISTORY_CAP),
+            battery_amps: VecDeque::new(),
+            disk_history: History::new(HISTORY_CAP),
+            net_history: HashMap::new(),
+            net_selected: None,
+            net_show_all: false,
+            process_sort: SortBy::default(),
+            gpu_histogram: false,
+            pressure_log: VecDeque::new(),
             interval,
         }
     }
@@ -35,7 +89,7 @@ impl App {
     pub fn run(
         &mut self,
         terminal: &mut ratatui::Terminal<impl ratatui::backend::Backend>,
-        rx: mpsc::Receiver<Metrics>,
+        mut feed: Feed,
     ) -> io::Result<()> {
         loop {
             // Poll for keyboard events
@@ -47,8 +101,28 @@ impl App {
                     match key.code {
                         KeyCode::Char('q') | KeyCode::Char('Q') => return Ok(()),
                         KeyCode::Esc => return Ok(()),
+                        KeyCode::Char('s') => self.process_sort = self.process_sort.next(),
+                        KeyCode::Char('h') => self.gpu_histogram = !self.gpu_histogram,
+                        KeyCode::Char('i') => self.next_interface(),
+                        KeyCode::Char('l') => self.net_show_all = !self.net_show_all,
                         _ => {}
                     }
+                    if let Feed::Replay(player) = &mut feed {
+                        match key.code {
+                            KeyCode::Char(' ') => player.toggle_pause(),
+                            KeyCode::Char('+') | KeyCode::Char('=') => player.faster(),
+                            KeyCode::Char('-') => player.slower(),
+                            KeyCode::Left => {
+                                let frames = player.seek(-SEEK_STEP_MS, HISTORY_CAP).to_vec();
+                                self.reset(frames);
+                            }
+                            KeyCode::Right => {
+                                let frames = player.seek(SEEK_STEP_MS, HISTORY_CAP).to_vec();
+                                self.reset(frames);
+                            }
+                            _ => {}
+                        }
+                    }
                     // Also handle Ctrl+C
                     if key.modifiers.contains(ratatui::crossterm::event::KeyModifiers::CONTROL)
                         && key.code == ratatui::crossterm::event::KeyCode::Char('c')
@@ -59,21 +133,150 @@ impl App {
             }
 
             // Check for new metrics (non-blocking)
-            while let Ok(metrics) = rx.try_recv() {
-                self.cpu_history.push(metrics.cpu.overall_percent as u64);
-                self.gpu_history.push(metrics.gpu.utilization as u64);
-                self.mem_history.push(metrics.memory.usage_percent() as u64);
-                let total_power = (metrics.power.cpu_watts + metrics.power.gpu_watts) * 10.0;
-                self.power_history.push(total_power as u64); // Store in 0.1W units
-                self.current = metrics;
+            match &mut feed {
+                Feed::Live(rx) => loop {
+                    match rx.try_recv() {
+                        Ok(metrics) => self.push(metrics, local_clock()),
+                        Err(mpsc::TryRecvError::Empty) => break,
+                        // The sampler runs forever, so only a failed
+                        // recording ends the feed
+                        Err(mpsc::TryRecvError::Disconnected) => {
+                            return Err(io::Error::other("the sample feed stopped"))
+                        }
+                    }
+                },
+                Feed::Replay(player) => {
+                    for frame in player.poll().to_vec() {
+                        self.push(frame.metrics, format_clock(frame.t_ms));
+                    }
+                }
             }
 
             // Render
-            terminal.draw(|frame| self.render(frame))?;
+            let player = match &feed {
+                Feed::Replay(player) => Some(player),
+                Feed::Live(_) => None,
+            };
+            terminal.draw(|frame| self.render(frame, player))?;
+        }
+    }
+
+    /// Add a sample taken at `time` (shown in the thermal pressure log).
+    fn push(&mut self, metrics: Metrics, time: String) {
+        if self.has_sample && metrics.thermal.pressure != self.current.thermal.pressure {
+            if self.pressure_log.len() == PRESSURE_LOG_CAP {
+                self.pressure_log.pop_front();
+            }
+            self.pressure_log.push_back(PressureChange {
+                time,
+                from: self.current.thermal.pressure,
+                to: metrics.thermal.pressure,
+                gpu_freq_mhz: metrics.gpu.freq_mhz,
+            });
+        }
+        self.cpu_history.push(metrics.cpu.overall_percent as u64);
+        self.gpu_history.push(metrics.gpu.utilization as u64);
+        self.mem_history.push(metrics.memory.usage_percent() as u64);
+        let total_power = metrics.power.total_watts() * 10.0;
+        self.power_history.push(total_power as u64); // Store in 0.1W units
+        self.thermal_history.push(metrics.thermal.cpu_temp_c as u64);
+        self.battery_history.push(metrics.battery.charge_percent() as u64);
+
+        // Plugging in or out makes the old rate meaningless
+        if metrics.battery.external_power != self.current.battery.external_power {
+            self.battery_amps.clear();
+        }
+        if self.battery_amps.len() == HISTORY_CAP {
+            self.battery_amps.pop_front();
+        }
+        self.battery_amps.push_back(metrics.battery.amperage_a);
+
+        let disk_total: f64 = metrics
+            .disks
+            .iter()
+            .map(|d| d.read_bytes_per_sec + d.write_bytes_per_sec)
+            .sum();
+        self.disk_history.push(disk_total as u64);
+
+        self.net_history
+            .retain(|name, _| metrics.network.iter().any(|i| &i.name == name));
+        for iface in &metrics.network {
+            let (rx, tx) = self
+                .net_history
+                .entry(iface.name.clone())
+                .or_insert_with(|| (History::new(HISTORY_CAP), History::new(HISTORY_CAP)));
+            rx.push(iface.rx_bytes_per_sec as u64);
+            tx.push(iface.tx_bytes_per_sec as u64);
+        }
+
+        self.current = metrics;
+        self.has_sample = true;
+    }
+
+    /// Clear the graphs and refill them from `frames` (after a seek).
+    fn reset(&mut self, frames: Vec<Frame>) {
+        self.cpu_history = History::new(HISTORY_CAP);
+        self.gpu_history = History::new(HISTORY_CAP);
+        self.mem_history = History::new(HISTORY_CAP);
+        self.power_history = History::new(HISTORY_CAP);
+        self.thermal_history = History::new(HISTORY_CAP);
+        self.battery_history = History::new(HISTORY_CAP);
+        self.battery_amps.clear();
+        self.disk_history = History::new(HISTORY_CAP);
+        self.net_history.clear();
+        self.pressure_log.clear();
+        self.current = Metrics::default();
+        self.has_sample = false;
+        for frame in frames {
+            self.push(frame.metrics, format_clock(frame.t_ms));
+        }
+    }
+
+    /// Interfaces shown in the network panel, in name order.
+    fn visible_interfaces(&self) -> Vec<&NetworkInterface> {
+        self.current
+            .network
+            .iter()
+            .filter(|i| self.net_show_all || !(i.loopback || i.inactive()))
+            .collect()
+    }
+
+    /// The interface in the network panel and its position among the
+    /// visible ones.
+    fn selected_interface(&self) -> Option<Selection<'_>> {
+        let visible = self.visible_interfaces();
+        let index = self
+            .net_selected
+            .as_ref()
+            .and_then(|name| visible.iter().position(|i| &i.name == name))
+            .or_else(|| {
+                (0..visible.len()).max_by_key(|&i| visible[i].rx_bytes + visible[i].tx_bytes)
+            })?;
+        Some(Selection {
+            interface: visible[index],
+            index,
+            count: visible.len(),
+        })
+    }
+
+    fn next_interface(&mut self) {
+        let visible = self.visible_interfaces();
+        let next = match self.selected_interface() {
+            Some(s) => visible[(s.index + 1) % s.count].name.clone(),
+            None => return,
+        };
+        self.net_selected = Some(next);
+    }
+
+    /// Battery current averaged over the history window.
+    fn battery_amps(&self) -> f64 {
+        if self.battery_amps.is_empty() {
+            return 0.0;
         }
+        self.battery_amps.iter().sum::<f64>() / self.battery_amps.len() as f64
     }
 
-    fn render(&self, frame: &mut ratatui::Frame) {
+    fn render(&self, frame: &mut ratatui::Frame, player: Option<&Player>) {
         let area = frame.area();
 
         // Outer layout: header, body, footer
@@ -85,12 +288,14 @@ impl App {
         .split(area);
 
         // Header
-        header::render(frame, outer[0], &self.soc);
+        header::render(frame, outer[0], &self.soc, self.current.thermal.pressure);
 
-        // Body: two rows
+        // Body: three rows of panels, then the process table
         let body = Layout::vertical([
             Constraint::Fill(1), // CPU + GPU row
             Constraint::Fill(1), // Memory + Power row
+            Constraint::Fill(1), // Network + Disk + Battery row
+            Constraint::Fill(1), // Processes
         ])
         .split(outer[1]);
 
@@ -101,18 +306,71 @@ impl App {
         ])
         .split(body[0]);
 
-        cpu_panel::render(frame, row1[0], self.current.cpu.overall_percent, &self.cpu_history);
-        gpu_panel::render(frame, row1[1], &self.current.gpu, &self.gpu_history);
+        cpu_panel::render(
+            frame,
+            row1[0],
+            &self.current.cpu,
+            &self.current.cpu_clusters,
+            &self.soc,
+            &self.cpu_history,
+        );
+        gpu_panel::render(
+            frame,
+            row1[1],
+            &self.current.gpu,
+            &self.soc,
+            &self.gpu_history,
+            self.gpu_histogram,
+        );
 
-        // Second row: Memory | Power
+        // Second row: Memory | Power | Thermal
         let row2 = Layout::horizontal([
-            Constraint::Fill(1),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
             Constraint::Fill(1),
         ])
         .split(body[1]);
 
         mem_panel::render(frame, row2[0], &self.current.memory, &self.mem_history);
         power_bar::render(frame, row2[1], &self.current.power, &self.power_history);
+        thermal_panel::render(
+            frame,
+            row2[2],
+            &self.current.thermal,
+            &self.pressure_log,
+            &self.thermal_history,
+        );
+
+        // Third row: Network | Disk | Battery, on machines that have one
+        let has_battery = self.current.battery.present;
+        let row3 = if has_battery {
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1), Constraint::Fill(1)])
+                .split(body[2])
+        } else {
+            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).split(body[2])
+        };
+
+        let selection = self.selected_interface();
+        let empty = (History::new(0), History::new(0));
+        let (rx_history, tx_history) = selection
+            .as_ref()
+            .and_then(|s| self.net_history.get(&s.interface.name))
+            .unwrap_or(&empty);
+        network_panel::render(frame, row3[0], selection, rx_history, tx_history, self.net_show_all);
+
+        disk_panel::render(frame, row3[1], &self.current.disks, &self.disk_history);
+
+        if has_battery {
+            battery_panel::render(
+                frame,
+                row3[2],
+                &self.current.battery,
+                self.battery_amps(),
+                &self.battery_history,
+            );
+        }
+
+        process_table::render(frame, body[3], &self.current.processes, self.process_sort);
 
         // Footer
         let interval_ms = self.interval.as_millis();
@@ -122,12 +380,72 @@ impl App {
             format!("{}ms", interval_ms)
         };
 
+        let (keys, status) = match player {
+            Some(player) => (
+                " q: quit  s: sort  h: GPU states  i/l: interfaces  space: pause  +/-: speed  ←/→: seek ".to_string(),
+                format!(
+                    " {} {} / {}  {}x  interval {} ",
+                    if player.paused() { "paused" } else { "replay" },
+                    format_clock(player.position_ms()),
+                    format_clock(player.duration_ms()),
+                    player.speed(),
+                    interval_str
+                ),
+            ),
+            None => (" q: quit  s: sort  h: GPU states  i/l: interfaces ".to_string(), format!(" interval {} ", interval_str)),
+        };
+
         let footer = Block::default()
             .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
-            .title(Line::from(" q: quit ").left_aligned())
-            .title(Line::from(format!(" interval {} ", interval_str)).right_aligned())
+            .title(Line::from(keys).left_aligned())
+            .title(Line::from(status).right_aligned())
             .border_style(Style::default().fg(Color::DarkGray));
 
         frame.render_widget(footer, outer[2]);
     }
 }
+
+/// Format milliseconds as m:ss.
+fn format_clock(ms: u64) -> String {
+    let secs = ms / 1000;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::thermal_pressure::ThermalPressure;
+
+    fn sample(pressure: ThermalPressure) -> Metrics {
+        let mut metrics = Metrics::default();
+        metrics.thermal.pressure = pressure;
+        metrics
+    }
+
+    fn logged(app: &App) -> Vec<(ThermalPressure, ThermalPressure)> {
+        app.pressure_log.iter().map(|c| (c.from, c.to)).collect()
+    }
+
+    #[test]
+    fn pressure_changes_are_logged_between_samples_only() {
+        let mut app = App::new(SocInfo::default(), Duration::from_secs(1));
+        app.push(sample(ThermalPressure::Heavy), "00:00".into());
+        assert!(logged(&app).is_empty());
+
+        app.push(sample(ThermalPressure::Heavy), "00:01".into());
+        app.push(sample(ThermalPressure::Nominal), "00:02".into());
+        assert_eq!(logged(&app), [(ThermalPressure::Heavy, ThermalPressure::Nominal)]);
+    }
+
+    #[test]
+    fn seeking_does_not_log_a_change_from_the_empty_sample() {
+        let mut app = App::new(SocInfo::default(), Duration::from_secs(1));
+        app.push(sample(ThermalPressure::Nominal), "00:00".into());
+        let frames = vec![
+            Frame { t_ms: 60_000, metrics: sample(ThermalPressure::Moderate) },
+            Frame { t_ms: 61_000, metrics: sample(ThermalPressure::Heavy) },
+        ];
+        app.reset(frames);
+        assert_eq!(logged(&app), [(ThermalPressure::Moderate, ThermalPressure::Heavy)]);
+    }
+}
@@ -0,0 +1,116 @@
+// Only the macOS SoC detection looks chips up
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+use serde::Deserialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Known chips, compiled in.
+const BUILTIN: &str = include_str!("chips.json");
+
+/// Published specs of one Apple Silicon chip.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChipSpec {
+    /// Brand string as reported by `machdep.cpu.brand_string`.
+    pub name: String,
+    /// Core counts of the full (unbinned) configuration.
+    pub p_cores: u32,
+    pub e_cores: u32,
+    /// GPU core counts the chip ships with, smallest first.
+    pub gpu_cores: Vec<u32>,
+    /// GPU DVFS table in MHz, ascending. Empty where it isn't known, so
+    /// only the one read from pmgr is used.
+    pub gpu_freqs: Vec<u32>,
+    pub memory_bandwidth_gbs: f64,
+}
+
+/// Look up a chip by brand string. Entries in the user's override file
+/// (see `override_path`, same format as the built-in table) win over
+/// the built-in ones, so new chips can be added without a rebuild.
+pub fn lookup(name: &str) -> Option<ChipSpec> {
+    let name = name.trim();
+    let matches = |spec: &ChipSpec| spec.name.eq_ignore_ascii_case(name);
+
+    if let Some(path) = override_path() {
+        match load_overrides(&path) {
+            Ok(specs) => {
+                if let Some(spec) = specs.into_iter().find(matches) {
+                    return Some(spec);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => eprintln!("macy: ignoring {}: {}", path.display(), e),
+        }
+    }
+
+    builtin().iter().find(|spec| matches(spec)).cloned()
+}
+
+/// The built-in table, parsed on first use.
+fn builtin() -> &'static [ChipSpec] {
+    static TABLE: OnceLock<Vec<ChipSpec>> = OnceLock::new();
+    TABLE.get_or_init(|| serde_json::from_str(BUILTIN).expect("built-in chip table is valid JSON"))
+}
+
+/// The user's chip table, `$XDG_CONFIG_HOME/macy/chips.json` or
+/// `~/.config/macy/chips.json`. `None` without a home directory.
+pub fn override_path() -> Option<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("macy").join("chips.json"))
+}
+
+fn load_overrides(path: &Path) -> io::Result<Vec<ChipSpec>> {
+    let text = std::fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn builtin_table_is_consistent() {
+        let specs = builtin();
+        assert!(!specs.is_empty());
+
+        let mut names = HashSet::new();
+        for spec in specs {
+            assert!(names.insert(spec.name.to_lowercase()), "{} is listed twice", spec.name);
+            assert!(spec.name.starts_with("Apple M"), "{}", spec.name);
+            assert!(spec.p_cores > 0 && spec.e_cores > 0, "{} core counts", spec.name);
+            assert!(!spec.gpu_cores.is_empty(), "{} has no GPU core counts", spec.name);
+            assert!(spec.gpu_cores.windows(2).all(|w| w[0] < w[1]), "{} GPU cores", spec.name);
+            // Empty means the table is only known from pmgr
+            assert!(spec.gpu_freqs.windows(2).all(|w| w[0] < w[1]), "{} GPU freqs", spec.name);
+            assert!(spec.gpu_freqs.iter().all(|&f| (100..3000).contains(&f)), "{} GPU freqs", spec.name);
+            assert!(spec.memory_bandwidth_gbs > 0.0, "{} bandwidth", spec.name);
+        }
+    }
+
+    #[test]
+    fn overrides_use_the_builtin_format() {
+        let dir = std::env::temp_dir().join(format!("macy-chips-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chips.json");
+        std::fs::write(
+            &path,
+            r#"[{ "name": "Apple M9", "p_cores": 1, "e_cores": 2, "gpu_cores": [3],
+                 "memory_bandwidth_gbs": 4, "gpu_freqs": [] }]"#,
+        )
+        .unwrap();
+        let specs = load_overrides(&path);
+        std::fs::write(&path, "{").unwrap();
+        let broken = load_overrides(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let specs = specs.unwrap();
+        assert_eq!(specs[0].name, "Apple M9");
+        assert!(specs[0].gpu_freqs.is_empty());
+        assert_eq!(broken.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,408 @@
+use crate::metrics::{self, Metrics};
+use crate::soc::SocInfo;
+use crate::sources::MetricSource;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long a client may take to send its request before we hang up.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run the sampler headlessly and serve the latest sample at `/metrics`
+/// in the Prometheus text exposition format.
+pub fn serve(
+    listen: &str,
+    soc: SocInfo,
+    sources: Vec<Box<dyn MetricSource>>,
+    interval: Duration,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(listen)?;
+    eprintln!("macy: serving http://{}/metrics", listener.local_addr()?);
+    serve_listener(listener, soc, sources, interval)
+}
+
+fn serve_listener(
+    listener: TcpListener,
+    soc: SocInfo,
+    sources: Vec<Box<dyn MetricSource>>,
+    interval: Duration,
+) -> io::Result<()> {
+    let latest: Arc<Mutex<Option<Metrics>>> = Arc::new(Mutex::new(None));
+    let rx = metrics::start_sampler(interval, sources);
+    {
+        let latest = Arc::clone(&latest);
+        thread::spawn(move || {
+            for m in rx {
+                *latest.lock().unwrap() = Some(m);
+            }
+        });
+    }
+
+    // One thread per connection, so a client that stalls before sending
+    // its request can't hold up other scrapes
+    let soc = Arc::new(soc);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let latest = Arc::clone(&latest);
+        let soc = Arc::clone(&soc);
+        thread::spawn(move || {
+            let body = {
+                let current = latest.lock().unwrap();
+                render(&soc, current.as_ref())
+            };
+            let _ = handle(stream, &body);
+        });
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, body: &str) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Drain the headers so closing the socket doesn't reset the connection
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("");
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => ("200 OK", "text/plain; version=0.0.4", body),
+        ("GET", _) => ("404 Not Found", "text/plain", "not found, try /metrics\n"),
+        _ => ("405 Method Not Allowed", "text/plain", "method not allowed\n"),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Render the exposition text. Before the first sample only the SoC info
+/// is known.
+fn render(soc: &SocInfo, m: Option<&Metrics>) -> String {
+    let mut out = String::new();
+
+    header(&mut out, "macy_soc_info", "Static SoC description, value is always 1.");
+    let _ = writeln!(
+        out,
+        "macy_soc_info{{chip=\"{}\",e_cores=\"{}\",p_cores=\"{}\",gpu_cores=\"{}\"}} 1",
+        escape_label(&soc.chip_name),
+        soc.e_cores,
+        soc.p_cores,
+        soc.gpu_cores
+    );
+
+    let Some(m) = m else {
+        return out;
+    };
+
+    header(&mut out, "macy_cpu_usage_percent", "Overall CPU usage.");
+    let _ = writeln!(out, "macy_cpu_usage_percent {}", m.cpu.overall_percent);
+
+    header(&mut out, "macy_cpu_core_usage_percent", "Per-core CPU usage.");
+    for (i, usage) in m.cpu.per_core.iter().enumerate() {
+        let _ = writeln!(out, "macy_cpu_core_usage_percent{{core=\"{}\"}} {}", i, usage);
+    }
+
+    if !m.cpu_clusters.is_empty() {
+        header(
+            &mut out,
+            "macy_cpu_cluster_frequency_hertz",
+            "Residency-weighted average frequency per CPU cluster.",
+        );
+        for cluster in &m.cpu_clusters {
+            let _ = writeln!(
+                out,
+                "macy_cpu_cluster_frequency_hertz{{cluster=\"{}\"}} {}",
+                escape_label(&cluster.name),
+                cluster.freq_mhz * 1_000_000.0
+            );
+        }
+    }
+
+    gauge(&mut out, "macy_gpu_utilization_percent", "GPU active residency.", m.gpu.utilization);
+    gauge(
+        &mut out,
+        "macy_gpu_frequency_hertz",
+        "Residency-weighted average GPU frequency.",
+        m.gpu.freq_mhz * 1_000_000.0,
+    );
+
+    header(
+        &mut out,
+        "macy_gpu_engine_utilization_percent",
+        "Accelerator utilization from PerformanceStatistics.",
+    );
+    for (engine, value) in [
+        ("device", m.gpu.device_utilization),
+        ("renderer", m.gpu.renderer_utilization),
+        ("tiler", m.gpu.tiler_utilization),
+    ] {
+        let _ = writeln!(out, "macy_gpu_engine_utilization_percent{{engine=\"{}\"}} {}", engine, value);
+    }
+    gauge(
+        &mut out,
+        "macy_gpu_memory_in_use_bytes",
+        "Unified memory in use by the GPU.",
+        m.gpu.in_use_memory_bytes as f64,
+    );
+    gauge(
+        &mut out,
+        "macy_gpu_memory_allocated_bytes",
+        "Unified memory allocated by the GPU.",
+        m.gpu.alloc_memory_bytes as f64,
+    );
+    gauge(&mut out, "macy_memory_used_bytes", "Memory in use.", m.memory.used_bytes as f64);
+    gauge(&mut out, "macy_memory_total_bytes", "Physical memory.", m.memory.total_bytes as f64);
+
+    header(&mut out, "macy_memory_breakdown_bytes", "Memory by Activity Monitor category.");
+    for (kind, bytes) in [
+        ("app", m.memory.app_bytes),
+        ("wired", m.memory.wired_bytes),
+        ("compressed", m.memory.compressed_bytes),
+        ("cached", m.memory.cached_bytes),
+        ("purgeable", m.memory.purgeable_bytes),
+        ("free", m.memory.free_bytes),
+    ] {
+        let _ = writeln!(out, "macy_memory_breakdown_bytes{{kind=\"{}\"}} {}", kind, bytes);
+    }
+
+    gauge(&mut out, "macy_swap_used_bytes", "Swap in use.", m.memory.swap_used_bytes as f64);
+    gauge(&mut out, "macy_swap_total_bytes", "Swap allocated.", m.memory.swap_total_bytes as f64);
+    gauge(
+        &mut out,
+        "macy_memory_pressure_level",
+        "Memory pressure: 0 normal, 1 warn, 2 critical.",
+        m.memory.pressure as u8 as f64,
+    );
+
+    header(&mut out, "macy_memory_paging_per_second", "Paging activity per second.");
+    for (kind, rate) in [
+        ("pagein", m.memory.pageins_per_sec),
+        ("pageout", m.memory.pageouts_per_sec),
+        ("compression", m.memory.compressions_per_sec),
+        ("decompression", m.memory.decompressions_per_sec),
+    ] {
+        let _ = writeln!(out, "macy_memory_paging_per_second{{kind=\"{}\"}} {}", kind, rate);
+    }
+
+    if !m.network.is_empty() {
+        header(&mut out, "macy_network_bytes_per_second", "Network throughput per interface.");
+        for iface in &m.network {
+            for (direction, rate) in [("rx", iface.rx_bytes_per_sec), ("tx", iface.tx_bytes_per_sec)] {
+                let _ = writeln!(
+                    out,
+                    "macy_network_bytes_per_second{{interface=\"{}\",direction=\"{}\"}} {}",
+                    escape_label(&iface.name), direction, rate
+                );
+            }
+        }
+        header(&mut out, "macy_network_packets_per_second", "Network packets per interface.");
+        for iface in &m.network {
+            for (direction, rate) in [("rx", iface.rx_packets_per_sec), ("tx", iface.tx_packets_per_sec)] {
+                let _ = writeln!(
+                    out,
+                    "macy_network_packets_per_second{{interface=\"{}\",direction=\"{}\"}} {}",
+                    escape_label(&iface.name), direction, rate
+                );
+            }
+        }
+    }
+
+    if !m.disks.is_empty() {
+        header(&mut out, "macy_disk_bytes_per_second", "Disk throughput per device.");
+        for disk in &m.disks {
+            for (direction, rate) in [("read", disk.read_bytes_per_sec), ("write", disk.write_bytes_per_sec)] {
+                let _ = writeln!(
+                    out,
+                    "macy_disk_bytes_per_second{{device=\"{}\",direction=\"{}\"}} {}",
+                    escape_label(&disk.name), direction, rate
+                );
+            }
+        }
+        header(&mut out, "macy_disk_operations_per_second", "Disk operations per device.");
+        for disk in &m.disks {
+            for (direction, rate) in [("read", disk.read_iops), ("write", disk.write_iops)] {
+                let _ = writeln!(
+                    out,
+                    "macy_disk_operations_per_second{{device=\"{}\",direction=\"{}\"}} {}",
+                    escape_label(&disk.name), direction, rate
+                );
+            }
+        }
+        header(&mut out, "macy_disk_latency_milliseconds", "Average time per disk operation.");
+        for disk in &m.disks {
+            let _ = writeln!(
+                out,
+                "macy_disk_latency_milliseconds{{device=\"{}\"}} {}",
+                escape_label(&disk.name),
+                disk.latency_ms
+            );
+        }
+    }
+
+    gauge(&mut out, "macy_cpu_power_watts", "CPU power draw.", m.power.cpu_watts);
+    gauge(&mut out, "macy_gpu_power_watts", "GPU power draw.", m.power.gpu_watts);
+    gauge(&mut out, "macy_ane_power_watts", "Neural Engine power draw.", m.power.ane_watts);
+    gauge(&mut out, "macy_dram_power_watts", "DRAM power draw.", m.power.dram_watts);
+    gauge(&mut out, "macy_isp_power_watts", "Image signal processor power draw.", m.power.isp_watts);
+    gauge(&mut out, "macy_media_power_watts", "Media engine power draw.", m.power.media_watts);
+    gauge(
+        &mut out,
+        "macy_package_power_watts",
+        "SoC/CPU package power draw, excluding DRAM.",
+        m.power.package_watts,
+    );
+
+    gauge(
+        &mut out,
+        "macy_system_power_watts",
+        "Whole-system power draw from the SMC.",
+        m.power.system_watts,
+    );
+
+    if m.thermal.cpu_temp_c > 0.0 {
+        gauge(&mut out, "macy_cpu_temperature_celsius", "Average CPU die temperature.", m.thermal.cpu_temp_c);
+    }
+    if m.thermal.gpu_temp_c > 0.0 {
+        gauge(&mut out, "macy_gpu_temperature_celsius", "Average GPU die temperature.", m.thermal.gpu_temp_c);
+    }
+    if !m.thermal.fans.is_empty() {
+        header(&mut out, "macy_fan_speed_rpm", "Fan speed.");
+        for (i, fan) in m.thermal.fans.iter().enumerate() {
+            let _ = writeln!(out, "macy_fan_speed_rpm{{fan=\"{}\"}} {}", i, fan.rpm);
+        }
+    }
+    gauge(
+        &mut out,
+        "macy_thermal_pressure_level",
+        "Thermal pressure: 0 nominal, 1 moderate, 2 heavy, 3 trapping, 4 sleeping.",
+        m.thermal.pressure as u8 as f64,
+    );
+
+    if m.battery.present {
+        gauge(&mut out, "macy_battery_charge_percent", "Battery charge.", m.battery.charge_percent());
+        gauge(
+            &mut out,
+            "macy_battery_power_watts",
+            "Battery power, positive while charging.",
+            m.battery.watts(),
+        );
+        gauge(
+            &mut out,
+            "macy_battery_health_percent",
+            "Full charge capacity relative to design capacity.",
+            m.battery.health_percent(),
+        );
+        gauge(&mut out, "macy_battery_cycle_count", "Battery charge cycles.", m.battery.cycle_count as f64);
+        gauge(
+            &mut out,
+            "macy_battery_temperature_celsius",
+            "Battery temperature.",
+            m.battery.temperature_c,
+        );
+        gauge(
+            &mut out,
+            "macy_battery_external_power",
+            "1 when on external power.",
+            m.battery.external_power as u8 as f64,
+        );
+    }
+
+    if !m.power.clusters.is_empty() {
+        header(&mut out, "macy_cpu_cluster_power_watts", "CPU power draw per cluster.");
+        for cluster in &m.power.clusters {
+            let _ = writeln!(
+                out,
+                "macy_cpu_cluster_power_watts{{cluster=\"{}\"}} {}",
+                escape_label(&cluster.name), cluster.watts
+            );
+        }
+    }
+
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Escape a label value per the exposition format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::disk::DiskDevice;
+    use crate::sources::network::NetworkInterface;
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape_label("a\nb"), "a\\nb");
+
+        let mut m = Metrics::default();
+        m.network.push(NetworkInterface {
+            name: "en\"0".to_string(),
+            ..NetworkInterface::default()
+        });
+        m.disks.push(DiskDevice {
+            name: "disk\\1".to_string(),
+            ..DiskDevice::default()
+        });
+        let text = render(&SocInfo::default(), Some(&m));
+
+        assert!(text.contains(r#"interface="en\"0",direction="rx""#), "{}", text);
+        assert!(text.contains(r#"device="disk\\1",direction="read""#), "{}", text);
+        assert!(text.contains(r#"macy_disk_latency_milliseconds{device="disk\\1"}"#), "{}", text);
+    }
+
+    #[test]
+    fn a_stalled_client_does_not_block_scrapes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            serve_listener(listener, SocInfo::default(), Vec::new(), Duration::from_secs(60))
+        });
+
+        // Connects and never sends a request
+        let _stalled = TcpStream::connect(addr).unwrap();
+
+        let mut scrape = TcpStream::connect(addr).unwrap();
+        scrape.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        scrape.write_all(b"GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+        let mut response = String::new();
+        io::Read::read_to_string(&mut scrape, &mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.contains("macy_soc_info"));
+    }
+}
@@ -1,64 +1,129 @@
 mod app;
+mod chips;
+mod exporter;
 mod metrics;
+mod output;
+mod recording;
 mod soc;
 mod sources;
 mod widgets;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
 
 #[derive(Parser)]
-#[command(name = "macy", about = "macOS Apple Silicon TUI System Monitor")]
+#[command(name = "macy", about = "macOS Apple Silicon and Linux TUI System Monitor")]
 struct Args {
     /// Sampling interval in milliseconds
-    #[arg(short, long, default_value = "1000")]
+    #[arg(short, long, default_value = "1000", global = true)]
     interval: u64,
 
     /// Print metrics to stdout instead of TUI (for debugging)
     #[arg(long)]
     print: bool,
+
+    /// Write samples to stdout in this format instead of running the TUI
+    #[arg(long, value_enum)]
+    format: Option<output::Format>,
+
+    /// Stop after this many samples (default: run forever, 3 with --print)
+    #[arg(long)]
+    count: Option<u64>,
+
+    /// Field delimiter for --format csv
+    #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+    delimiter: char,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn main() -> io::Result<()> {
-    // Platform check
-    if cfg!(not(target_os = "macos")) {
-        eprintln!("macy only runs on macOS");
-        std::process::exit(1);
-    }
+#[derive(Subcommand)]
+enum Command {
+    /// Run the TUI and save every sample to a file
+    Record {
+        /// Recording file to write
+        file: PathBuf,
+    },
+    /// Play back a recording in the TUI
+    Replay {
+        /// Recording file to read
+        file: PathBuf,
+    },
+    /// Serve the latest sample as Prometheus metrics over HTTP
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:9101")]
+        listen: String,
+    },
+}
 
-    if cfg!(not(target_arch = "aarch64")) {
-        eprintln!("macy requires Apple Silicon (arm64)");
-        std::process::exit(1);
+/// A CSV delimiter that can't appear in a number or a row, so the output
+/// still parses back.
+fn parse_delimiter(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return Err("must be a single character".to_string());
+    };
+    if c.is_ascii_digit() || matches!(c, '.' | '-' | '"' | '\n' | '\r') {
+        return Err(format!("{:?} can't separate numeric fields", c));
     }
+    Ok(c)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+compile_error!("macy only supports macOS and Linux");
 
+#[cfg(all(target_os = "macos", not(target_arch = "aarch64")))]
+compile_error!("macy requires Apple Silicon (arm64) on macOS");
+
+fn main() -> io::Result<()> {
     let args = Args::parse();
     let interval = Duration::from_millis(args.interval);
 
+    // Replay needs no hardware access at all
+    if let Some(Command::Replay { file }) = &args.command {
+        let rec = recording::load(file)?;
+        let player = recording::Player::new(rec.frames);
+        return run_tui(app::App::new(rec.soc, rec.interval), app::Feed::Replay(player));
+    }
+
     // Detect SoC info
     let soc = soc::detect();
 
-    if args.print {
-        println!("SoC: {}", soc);
-        println!("GPU freqs: {:?}", soc.gpu_freqs);
-        let rx = metrics::start_sampler(interval, soc);
-        for _ in 0..3 {
-            if let Ok(m) = rx.recv() {
-                println!(
-                    "CPU: {:.1}% | GPU: {:.1}% @ {:.0}MHz | Mem: {:.1}/{:.1}GB | Power: CPU {:.1}W GPU {:.1}W",
-                    m.cpu.overall_percent,
-                    m.gpu.utilization, m.gpu.freq_mhz,
-                    m.memory.used_gb(), m.memory.total_gb(),
-                    m.power.cpu_watts, m.power.gpu_watts,
-                );
-            }
-        }
-        return Ok(());
+    if let Some(Command::Serve { listen }) = &args.command {
+        let sources = sources::default_sources(&soc);
+        return exporter::serve(listen, soc, sources, interval);
+    }
+
+    if args.print || args.format.is_some() {
+        let format = args.format.unwrap_or(output::Format::Text);
+        let count = args.count.or(if args.print { Some(3) } else { None });
+        let sources = sources::default_sources(&soc);
+        return output::run(format, &soc, sources, interval, count, args.delimiter);
     }
 
     // Start background sampler
-    let rx = metrics::start_sampler(interval, soc.clone());
+    let mut rx = metrics::start_sampler(interval, sources::default_sources(&soc));
+
+    let mut recorder = None;
+    if let Some(Command::Record { file }) = &args.command {
+        let (tee_rx, writer) = recording::record(file, &soc, interval, rx)?;
+        rx = tee_rx;
+        recorder = Some(writer);
+    }
+
+    let result = run_tui(app::App::new(soc, interval), app::Feed::Live(rx));
+    if let (Err(_), Some(writer)) = (&result, recorder) {
+        // A failed write ends the feed; its error says why
+        writer.join().map_err(|_| io::Error::other("recording thread panicked"))??;
+    }
+    result
+}
 
+fn run_tui(mut app: app::App, feed: app::Feed) -> io::Result<()> {
     // Setup terminal
     ratatui::crossterm::terminal::enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -70,8 +135,7 @@ fn main() -> io::Result<()> {
     let mut terminal = ratatui::Terminal::new(backend)?;
 
     // Run the app
-    let mut app = app::App::new(soc, interval);
-    let result = app.run(&mut terminal, rx);
+    let result = app.run(&mut terminal, feed);
 
     // Restore terminal
     ratatui::crossterm::terminal::disable_raw_mode()?;
@@ -83,3 +147,32 @@ fn main() -> io::Result<()> {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_is_accepted_after_subcommands() {
+        for argv in [
+            &["macy", "-i", "500", "serve"][..],
+            &["macy", "serve", "-i", "500"],
+            &["macy", "record", "out.jsonl", "-i", "500"],
+            &["macy", "replay", "out.jsonl", "--interval", "500"],
+        ] {
+            let args = Args::try_parse_from(argv).unwrap();
+            assert_eq!(args.interval, 500, "{:?}", argv);
+        }
+    }
+
+    #[test]
+    fn delimiters_that_break_numbers_are_rejected() {
+        for delimiter in [",", ";", "\t", "|", " "] {
+            let args = Args::try_parse_from(["macy", "--delimiter", delimiter]).unwrap();
+            assert_eq!(args.delimiter.to_string(), delimiter);
+        }
+        for delimiter in [".", "-", "\"", "0", "7", "\n", ",,", ""] {
+            assert!(Args::try_parse_from(["macy", "--delimiter", delimiter]).is_err(), "{:?}", delimiter);
+        }
+    }
+}
@@ -1,56 +1,71 @@
-use crate::soc::SocInfo;
-use crate::sources::cpu::{CpuTracker, CpuUsage};
-use crate::sources::ioreport::{self, GpuMetrics, IOReportSubscription, PowerMetrics};
-use crate::sources::memory::{self, MemoryInfo};
+use crate::sources::battery::BatteryInfo;
+use crate::sources::cpu::CpuUsage;
+use crate::sources::disk::DiskDevice;
+use crate::sources::ioreport::{CpuClusterMetrics, GpuMetrics, PowerMetrics};
+use crate::sources::memory::MemoryInfo;
+use crate::sources::network::NetworkInterface;
+use crate::sources::process::{self, ProcessInfo};
+use crate::sources::smc::ThermalMetrics;
+use crate::sources::MetricSource;
+use serde::{Deserialize, Serialize};
 use std::sync::mpsc;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 /// All metrics collected in one sample.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Metrics {
     pub cpu: CpuUsage,
+    /// Per-cluster activity and frequency, where IOReport provides it.
+    pub cpu_clusters: Vec<CpuClusterMetrics>,
     pub gpu: GpuMetrics,
     pub memory: MemoryInfo,
     pub power: PowerMetrics,
+    pub thermal: ThermalMetrics,
+    pub battery: BatteryInfo,
+    /// Every interface, ordered by name.
+    pub network: Vec<NetworkInterface>,
+    /// Whole disks, ordered by name.
+    pub disks: Vec<DiskDevice>,
+    /// The top processes, ordered by CPU usage.
+    pub processes: Vec<ProcessInfo>,
 }
 
-impl Default for Metrics {
-    fn default() -> Self {
-        Self {
-            cpu: CpuUsage::default(),
-            gpu: GpuMetrics::default(),
-            memory: MemoryInfo::default(),
-            power: PowerMetrics::default(),
-        }
-    }
-}
-
-/// Start the background sampler thread. Returns a receiver for metrics.
-pub fn start_sampler(interval: Duration, soc: SocInfo) -> mpsc::Receiver<Metrics> {
+/// Start the background sampler thread over the given sources.
+/// Returns a receiver for metrics.
+pub fn start_sampler(
+    interval: Duration,
+    sources: Vec<Box<dyn MetricSource>>,
+) -> mpsc::Receiver<Metrics> {
     let (tx, rx) = mpsc::channel();
 
     thread::spawn(move || {
-        sampler_loop(tx, interval, &soc);
+        sampler_loop(tx, interval, sources);
     });
 
     rx
 }
 
-fn sampler_loop(tx: mpsc::Sender<Metrics>, interval: Duration, soc: &SocInfo) {
-    let mut cpu_tracker = CpuTracker::new();
-
-    // Subscribe to IOReport channels for GPU stats and energy
-    let subscription = ioreport::create_subscription(&["GPU Stats", "Energy Model"]);
-
-    // Initial sleep to let the first CPU tick delta accumulate
-    thread::sleep(interval);
+fn sampler_loop(
+    tx: mpsc::Sender<Metrics>,
+    interval: Duration,
+    mut sources: Vec<Box<dyn MetricSource>>,
+) {
+    // Sources that can't initialize (missing permissions, unsupported
+    // hardware) are left out; their fields stay at their defaults.
+    sources.retain_mut(|source| source.init().is_ok());
 
     loop {
-        let metrics = match &subscription {
-            Some(sub) => sample_with_ioreport(sub, &mut cpu_tracker, interval, &soc.gpu_freqs),
-            None => sample_without_ioreport(&mut cpu_tracker),
-        };
+        // Let the tick/energy deltas accumulate
+        thread::sleep(interval);
+
+        let mut metrics = Metrics::default();
+        for source in sources.iter_mut() {
+            source.sample(&mut metrics);
+        }
+        // Fields derived from more than one source
+        process::attribute_power(&mut metrics);
 
         if tx.send(metrics).is_err() {
             break;
@@ -58,52 +73,60 @@ fn sampler_loop(tx: mpsc::Sender<Metrics>, interval: Duration, soc: &SocInfo) {
     }
 }
 
-fn sample_with_ioreport(
-    sub: &IOReportSubscription,
-    cpu_tracker: &mut CpuTracker,
-    interval: Duration,
-    gpu_freqs: &[u32],
-) -> Metrics {
-    let s1 = ioreport::create_sample(sub);
-    let t1 = Instant::now();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::ioreport::CpuClusterMetrics;
+    use std::io;
 
-    thread::sleep(interval);
+    /// Appends a cluster named after itself, so the order shows in the sample.
+    struct FakeSource {
+        name: &'static str,
+        fail_init: bool,
+    }
 
-    let s2 = ioreport::create_sample(sub);
-    let elapsed_ns = t1.elapsed().as_nanos() as u64;
+    impl MetricSource for FakeSource {
+        fn describe(&self) -> &str {
+            self.name
+        }
 
-    let delta = ioreport::create_delta(s1, s2);
-    ioreport::release_sample(s1);
-    ioreport::release_sample(s2);
+        fn init(&mut self) -> io::Result<()> {
+            if self.fail_init {
+                return Err(io::Error::other("unsupported"));
+            }
+            Ok(())
+        }
 
-    let (gpu_util, gpu_freq) = ioreport::parse_gpu_stats(delta, gpu_freqs);
-    let power = ioreport::parse_power(delta, elapsed_ns);
+        fn sample(&mut self, metrics: &mut Metrics) {
+            metrics.cpu_clusters.push(CpuClusterMetrics {
+                name: self.name.to_string(),
+                ..CpuClusterMetrics::default()
+            });
+        }
+    }
 
-    ioreport::release_sample(delta);
+    fn fake(name: &'static str, fail_init: bool) -> Box<dyn MetricSource> {
+        Box::new(FakeSource { name, fail_init })
+    }
 
-    let cpu = cpu_tracker.sample();
-    let mem = memory::get_memory_info();
+    #[test]
+    fn failed_sources_are_dropped_and_the_rest_sample_in_order() {
+        let sources = vec![fake("a", false), fake("broken", true), fake("b", false), fake("c", false)];
+        let rx = start_sampler(Duration::from_millis(1), sources);
 
-    Metrics {
-        cpu,
-        gpu: GpuMetrics {
-            utilization: gpu_util,
-            freq_mhz: gpu_freq,
-            power_watts: power.gpu_watts,
-        },
-        memory: mem,
-        power,
+        for _ in 0..3 {
+            let metrics = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            let names: Vec<&str> = metrics.cpu_clusters.iter().map(|c| c.name.as_str()).collect();
+            assert_eq!(names, ["a", "b", "c"]);
+        }
     }
-}
 
-fn sample_without_ioreport(cpu_tracker: &mut CpuTracker) -> Metrics {
-    let cpu = cpu_tracker.sample();
-    let mem = memory::get_memory_info();
-
-    Metrics {
-        cpu,
-        gpu: GpuMetrics::default(),
-        memory: mem,
-        power: PowerMetrics::default(),
+    #[test]
+    fn every_sample_starts_from_defaults() {
+        let rx = start_sampler(Duration::from_millis(1), vec![fake("a", false)]);
+        for _ in 0..2 {
+            let metrics = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(metrics.cpu_clusters.len(), 1);
+        }
     }
 }
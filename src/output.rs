@@ -0,0 +1,365 @@
+use crate::chips;
+use crate::metrics::{self, Metrics};
+use crate::soc::SocInfo;
+use crate::sources::network::NetworkInterface;
+use crate::sources::thermal_pressure::{PressureChange, ThermalPressure};
+use crate::sources::MetricSource;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Output format for the non-TUI mode.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    /// Human-readable summary lines
+    Text,
+    /// One JSON object per sample (JSON Lines)
+    Json,
+    /// Delimited values with a header row
+    Csv,
+}
+
+/// One CSV column: its header name and how to read it from a sample.
+type Column = (&'static str, fn(&Metrics) -> f64);
+
+/// CSV columns in output order, after the leading `timestamp` and before
+/// the per-core `cpu0_percent`, `cpu1_percent`, ... block, which comes last
+/// because its width depends on the machine.
+/// Only ever append to this list: dashboards rely on column positions.
+const CSV_COLUMNS: &[Column] = &[
+    ("cpu_percent", |m| m.cpu.overall_percent),
+    ("gpu_utilization", |m| m.gpu.utilization),
+    ("gpu_freq_mhz", |m| m.gpu.freq_mhz),
+    ("mem_used_bytes", |m| m.memory.used_bytes as f64),
+    ("mem_total_bytes", |m| m.memory.total_bytes as f64),
+    ("cpu_watts", |m| m.power.cpu_watts),
+    ("gpu_watts", |m| m.power.gpu_watts),
+    ("package_watts", |m| m.power.package_watts),
+    ("dram_watts", |m| m.power.dram_watts),
+    ("ecpu_freq_mhz", |m| cluster_freq(m, 'E')),
+    ("pcpu_freq_mhz", |m| cluster_freq(m, 'P')),
+    ("ecpu_watts", |m| cluster_watts(m, 'E')),
+    ("pcpu_watts", |m| cluster_watts(m, 'P')),
+    ("ane_watts", |m| m.power.ane_watts),
+    ("isp_watts", |m| m.power.isp_watts),
+    ("media_watts", |m| m.power.media_watts),
+    ("total_watts", |m| m.power.total_watts()),
+    ("mem_app_bytes", |m| m.memory.app_bytes as f64),
+    ("mem_wired_bytes", |m| m.memory.wired_bytes as f64),
+    ("mem_compressed_bytes", |m| m.memory.compressed_bytes as f64),
+    ("mem_cached_bytes", |m| m.memory.cached_bytes as f64),
+    ("mem_purgeable_bytes", |m| m.memory.purgeable_bytes as f64),
+    ("mem_free_bytes", |m| m.memory.free_bytes as f64),
+    ("swap_total_bytes", |m| m.memory.swap_total_bytes as f64),
+    ("swap_used_bytes", |m| m.memory.swap_used_bytes as f64),
+    ("mem_pressure", |m| m.memory.pressure as u8 as f64),
+    ("pageins_per_sec", |m| m.memory.pageins_per_sec),
+    ("pageouts_per_sec", |m| m.memory.pageouts_per_sec),
+    ("compressions_per_sec", |m| m.memory.compressions_per_sec),
+    ("decompressions_per_sec", |m| m.memory.decompressions_per_sec),
+    ("gpu_device_utilization", |m| m.gpu.device_utilization),
+    ("gpu_renderer_utilization", |m| m.gpu.renderer_utilization),
+    ("gpu_tiler_utilization", |m| m.gpu.tiler_utilization),
+    ("gpu_mem_in_use_bytes", |m| m.gpu.in_use_memory_bytes as f64),
+    ("gpu_mem_alloc_bytes", |m| m.gpu.alloc_memory_bytes as f64),
+    ("cpu_temp_c", |m| m.thermal.cpu_temp_c),
+    ("gpu_temp_c", |m| m.thermal.gpu_temp_c),
+    ("system_watts", |m| m.power.system_watts),
+    ("thermal_pressure", |m| m.thermal.pressure as u8 as f64),
+    ("battery_percent", |m| m.battery.charge_percent()),
+    ("battery_watts", |m| m.battery.watts()),
+    ("battery_health_percent", |m| m.battery.health_percent()),
+    ("battery_cycles", |m| m.battery.cycle_count as f64),
+    ("battery_temp_c", |m| m.battery.temperature_c),
+    ("net_rx_bytes_per_sec", |m| network_total(m, |i| i.rx_bytes_per_sec)),
+    ("net_tx_bytes_per_sec", |m| network_total(m, |i| i.tx_bytes_per_sec)),
+    ("disk_read_bytes_per_sec", |m| m.disks.iter().map(|d| d.read_bytes_per_sec).sum()),
+    ("disk_write_bytes_per_sec", |m| m.disks.iter().map(|d| d.write_bytes_per_sec).sum()),
+    ("disk_read_iops", |m| m.disks.iter().map(|d| d.read_iops).sum()),
+    ("disk_write_iops", |m| m.disks.iter().map(|d| d.write_iops).sum()),
+];
+
+/// Clusters of one kind are "E", "P" or, on chips with several P-clusters,
+/// "P0", "P1", ...
+fn is_cluster_kind(name: &str, kind: char) -> bool {
+    name.starts_with(kind)
+}
+
+/// Activity-weighted frequency of all clusters of one kind.
+fn cluster_freq(m: &Metrics, kind: char) -> f64 {
+    let clusters = m.cpu_clusters.iter().filter(|c| is_cluster_kind(&c.name, kind));
+    let (weighted, active) = clusters.fold((0.0, 0.0), |(weighted, active), c| {
+        (weighted + c.freq_mhz * c.active_percent, active + c.active_percent)
+    });
+    if active > 0.0 {
+        weighted / active
+    } else {
+        0.0
+    }
+}
+
+/// Sum over the non-loopback interfaces.
+fn network_total(m: &Metrics, value: fn(&NetworkInterface) -> f64) -> f64 {
+    m.network.iter().filter(|i| !i.loopback).map(value).sum()
+}
+
+/// Power of all clusters of one kind (P0 + P1 on multi-cluster chips).
+fn cluster_watts(m: &Metrics, kind: char) -> f64 {
+    m.power
+        .clusters
+        .iter()
+        .filter(|c| is_cluster_kind(&c.name, kind))
+        .map(|c| c.watts)
+        .sum()
+}
+
+#[derive(Serialize)]
+struct JsonSample<'a> {
+    /// Seconds since the Unix epoch.
+    timestamp: f64,
+    #[serde(flatten)]
+    metrics: &'a Metrics,
+}
+
+/// Sample and write metrics to stdout, for `count` samples or forever.
+/// `delimiter` separates CSV fields.
+pub fn run(
+    format: Format,
+    soc: &SocInfo,
+    sources: Vec<Box<dyn MetricSource>>,
+    interval: Duration,
+    count: Option<u64>,
+    delimiter: char,
+) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    let result = write_samples(&mut out, format, soc, sources, interval, count, delimiter);
+
+    // The reader going away (e.g. `macy --format json | head`) is a normal end
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => other,
+    }
+}
+
+fn write_samples(
+    out: &mut impl Write,
+    format: Format,
+    soc: &SocInfo,
+    sources: Vec<Box<dyn MetricSource>>,
+    interval: Duration,
+    count: Option<u64>,
+    delimiter: char,
+) -> io::Result<()> {
+    if format == Format::Text {
+        writeln!(out, "SoC: {}", soc)?;
+        writeln!(out, "GPU freqs: {:?}", soc.gpu_freqs)?;
+        if !soc.ecpu_freqs.is_empty() || !soc.pcpu_freqs.is_empty() {
+            writeln!(out, "CPU freqs: E {:?} P {:?}", soc.ecpu_freqs, soc.pcpu_freqs)?;
+        }
+        if soc.memory_bandwidth_gbs > 0.0 {
+            writeln!(out, "Memory bandwidth: {} GB/s", soc.memory_bandwidth_gbs)?;
+        }
+        if soc.unknown_chip {
+            match chips::override_path() {
+                Some(path) => writeln!(out, "Chip not in the chip table; add it to {}", path.display())?,
+                None => writeln!(out, "Chip not in the chip table")?,
+            }
+        }
+        let names: Vec<&str> = sources.iter().map(|s| s.describe()).collect();
+        writeln!(out, "Sources: {}", names.join(", "))?;
+    }
+
+    // The per-core column count is fixed by the first sample
+    let mut csv_cores = None;
+    let mut pressure: Option<ThermalPressure> = None;
+
+    let rx = metrics::start_sampler(interval, sources);
+    for m in rx.iter().take(count.map_or(usize::MAX, |n| n as usize)) {
+        match pressure {
+            Some(from) if format == Format::Text && m.thermal.pressure != from => {
+                let change = PressureChange {
+                    time: local_clock(),
+                    from,
+                    to: m.thermal.pressure,
+                    gpu_freq_mhz: m.gpu.freq_mhz,
+                };
+                writeln!(out, "Thermal pressure: {}", change)?;
+            }
+            _ => {}
+        }
+        pressure = Some(m.thermal.pressure);
+
+        match format {
+            Format::Text => writeln!(
+                out,
+                "CPU: {:.1}% | GPU: {:.1}% @ {:.0}MHz | Mem: {:.1}/{:.1}GB | Power: CPU {:.1}W GPU {:.1}W",
+                m.cpu.overall_percent,
+                m.gpu.utilization, m.gpu.freq_mhz,
+                m.memory.used_gb(), m.memory.total_gb(),
+                m.power.cpu_watts, m.power.gpu_watts,
+            )?,
+            Format::Json => {
+                let sample = JsonSample {
+                    timestamp: unix_seconds(),
+                    metrics: &m,
+                };
+                serde_json::to_writer(&mut *out, &sample)?;
+                writeln!(out)?;
+            }
+            Format::Csv => {
+                let cores = match csv_cores {
+                    Some(cores) => cores,
+                    None => {
+                        let cores = m.cpu.per_core.len();
+                        write_csv_header(out, cores, delimiter)?;
+                        csv_cores = Some(cores);
+                        cores
+                    }
+                };
+                write_csv_row(out, &m, cores, delimiter)?;
+            }
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn write_csv_header(out: &mut impl Write, cores: usize, delimiter: char) -> io::Result<()> {
+    let mut names = vec!["timestamp".to_string()];
+    names.extend(CSV_COLUMNS.iter().map(|(name, _)| name.to_string()));
+    names.extend((0..cores).map(|i| format!("cpu{}_percent", i)));
+    writeln!(out, "{}", names.join(&delimiter.to_string()))
+}
+
+fn write_csv_row(
+    out: &mut impl Write,
+    m: &Metrics,
+    cores: usize,
+    delimiter: char,
+) -> io::Result<()> {
+    let mut fields = vec![format!("{:.3}", unix_seconds())];
+    fields.extend(CSV_COLUMNS.iter().map(|(_, value)| csv_number(value(m))));
+    // Pad or cut to the header's core count so the row matches the header
+    fields.extend((0..cores).map(|i| csv_number(m.cpu.per_core.get(i).copied().unwrap_or(0.0))));
+    writeln!(out, "{}", fields.join(&delimiter.to_string()))
+}
+
+/// Integers (byte counts) without a fraction, everything else to 3 places.
+fn csv_number(value: f64) -> String {
+    // Sums over nothing are -0.0
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    if value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.3}", value)
+    }
+}
+
+fn unix_seconds() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// The local wall-clock time as HH:MM:SS.
+pub fn local_clock() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs()) as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return "--:--:--".to_string();
+    }
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::ioreport::{ClusterPower, CpuClusterMetrics, PowerMetrics};
+
+    fn header(cores: usize) -> String {
+        let mut out = Vec::new();
+        write_csv_header(&mut out, cores, ',').unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_header_prefix_is_stable() {
+        // Never reorder these; new columns go at the end of CSV_COLUMNS
+        let prefix = "timestamp,cpu_percent,gpu_utilization,gpu_freq_mhz,mem_used_bytes,\
+                      mem_total_bytes,cpu_watts,gpu_watts,package_watts,dram_watts,\
+                      ecpu_freq_mhz,pcpu_freq_mhz,ecpu_watts,pcpu_watts,ane_watts,isp_watts,\
+                      media_watts,total_watts,";
+        assert!(header(4).starts_with(prefix), "{}", header(4));
+    }
+
+    #[test]
+    fn csv_per_core_columns_come_last() {
+        let scalars = |cores| {
+            let header = header(cores);
+            let names: Vec<String> = header.trim_end().split(',').map(str::to_string).collect();
+            names[..names.len() - cores].to_vec()
+        };
+        assert_eq!(scalars(2), scalars(24));
+        assert!(header(2).trim_end().ends_with(",cpu0_percent,cpu1_percent"));
+    }
+
+    #[test]
+    fn csv_numbers() {
+        assert_eq!(csv_number(-0.0), "0");
+        assert_eq!(csv_number(0.0), "0");
+        assert_eq!(csv_number(4096.0), "4096");
+        assert_eq!(csv_number(1.23456), "1.235");
+        assert_eq!(csv_number(-2.5), "-2.500");
+        assert_eq!(csv_number(std::iter::empty::<f64>().sum()), "0");
+    }
+
+    #[test]
+    fn cluster_columns_match_multi_cluster_names() {
+        let m = Metrics {
+            cpu_clusters: vec![
+                CpuClusterMetrics { name: "E".into(), active_percent: 50.0, freq_mhz: 1000.0 },
+                CpuClusterMetrics { name: "P0".into(), active_percent: 75.0, freq_mhz: 3000.0 },
+                CpuClusterMetrics { name: "P1".into(), active_percent: 25.0, freq_mhz: 1000.0 },
+            ],
+            power: PowerMetrics {
+                clusters: vec![
+                    ClusterPower { name: "E".into(), watts: 0.5, cores: Vec::new() },
+                    ClusterPower { name: "P0".into(), watts: 3.0, cores: Vec::new() },
+                    ClusterPower { name: "P1".into(), watts: 1.0, cores: Vec::new() },
+                ],
+                ..PowerMetrics::default()
+            },
+            ..Metrics::default()
+        };
+
+        assert_eq!(cluster_freq(&m, 'E'), 1000.0);
+        assert_eq!(cluster_freq(&m, 'P'), 2500.0);
+        assert_eq!(cluster_watts(&m, 'E'), 0.5);
+        assert_eq!(cluster_watts(&m, 'P'), 4.0);
+
+        // Linux has no clusters at all
+        let empty = Metrics::default();
+        assert_eq!(csv_number(cluster_freq(&empty, 'P')), "0");
+        assert_eq!(csv_number(cluster_watts(&empty, 'P')), "0");
+    }
+
+    #[test]
+    fn csv_rows_match_the_header_width() {
+        let mut metrics = Metrics::default();
+        metrics.cpu.per_core = vec![10.0, 20.0, 30.0];
+
+        let mut out = Vec::new();
+        write_csv_row(&mut out, &metrics, 2, ';').unwrap();
+        let row = String::from_utf8(out).unwrap();
+        let fields: Vec<&str> = row.trim_end().split(';').collect();
+
+        assert_eq!(fields.len(), header(2).split(',').count());
+        assert_eq!(fields[fields.len() - 2..], ["10", "20"]);
+    }
+}
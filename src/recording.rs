@@ -0,0 +1,436 @@
+use crate::metrics::Metrics;
+use crate::soc::SocInfo;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Recordings are JSON Lines: one `Header` line followed by one `Frame` per
+// sample. Every struct reachable from `Metrics` uses `#[serde(default)]`, so
+// fields added later simply read as zero from older files. Bump the version
+// only for changes that need an explicit migration in `load`.
+//
+// Version history:
+//   1 - initial format
+const FORMAT_NAME: &str = "macy-recording";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    /// Wall-clock start of the recording, in ms since the Unix epoch.
+    started_at_ms: u64,
+    interval_ms: u64,
+    soc: SocInfo,
+}
+
+/// One recorded sample.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    /// Time since the start of the recording.
+    pub t_ms: u64,
+    pub metrics: Metrics,
+}
+
+/// A recording loaded back from disk.
+pub struct Recording {
+    pub soc: SocInfo,
+    pub interval: Duration,
+    pub frames: Vec<Frame>,
+}
+
+/// Write every sample from `rx` to `path` and pass it on unchanged.
+/// Returns the receiver the TUI should read from and the writer thread. If
+/// a write fails the thread ends with the error and the receiver
+/// disconnects.
+pub fn record(
+    path: &Path,
+    soc: &SocInfo,
+    interval: Duration,
+    rx: mpsc::Receiver<Metrics>,
+) -> io::Result<(mpsc::Receiver<Metrics>, thread::JoinHandle<io::Result<()>>)> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    let started_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let header = Header {
+        format: FORMAT_NAME.to_string(),
+        version: FORMAT_VERSION,
+        started_at_ms,
+        interval_ms: interval.as_millis() as u64,
+        soc: soc.clone(),
+    };
+    write_line(&mut out, &header)?;
+
+    Ok(spawn_writer(out, path.to_path_buf(), rx))
+}
+
+/// The writer thread behind `record`: tee every sample from `rx` into
+/// `out` as a frame. `path` only names the file in errors.
+fn spawn_writer(
+    mut out: impl Write + Send + 'static,
+    path: PathBuf,
+    rx: mpsc::Receiver<Metrics>,
+) -> (mpsc::Receiver<Metrics>, thread::JoinHandle<io::Result<()>>) {
+    let (tx, tee_rx) = mpsc::channel();
+    let writer = thread::spawn(move || {
+        let start = Instant::now();
+        for metrics in rx {
+            let frame = Frame {
+                t_ms: start.elapsed().as_millis() as u64,
+                metrics,
+            };
+            write_line(&mut out, &frame)
+                .map_err(|e| io::Error::new(e.kind(), format!("writing {}: {}", path.display(), e)))?;
+            if tx.send(frame.metrics).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    });
+    (tee_rx, writer)
+}
+
+/// Serialize one JSON line and flush, so an interrupted recording loses at
+/// most the sample being written.
+fn write_line(out: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, value)?;
+    out.write_all(b"\n")?;
+    out.flush()
+}
+
+/// Load a recording written by `record`.
+pub fn load(path: &Path) -> io::Result<Recording> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+
+    let first = lines
+        .next()
+        .ok_or_else(|| invalid("recording is empty".to_string()))??;
+    let header: Header = serde_json::from_str(&first)
+        .map_err(|e| invalid(format!("not a macy recording: {}", e)))?;
+    if header.format != FORMAT_NAME {
+        return Err(invalid(format!("not a macy recording: {}", header.format)));
+    }
+    if header.version > FORMAT_VERSION {
+        return Err(invalid(format!(
+            "recording version {} is newer than this macy supports ({})",
+            header.version, FORMAT_VERSION
+        )));
+    }
+
+    let mut frames = Vec::new();
+    let mut pending_error = None;
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // A bad line is only tolerated at the very end, where an
+        // interrupted write leaves a truncated sample.
+        if let Some(e) = pending_error.take() {
+            return Err(e);
+        }
+        match serde_json::from_str::<Frame>(&line) {
+            Ok(frame) => frames.push(frame),
+            Err(e) => pending_error = Some(invalid(format!("line {}: {}", i + 2, e))),
+        }
+    }
+
+    Ok(Recording {
+        soc: header.soc,
+        interval: Duration::from_millis(header.interval_ms),
+        frames,
+    })
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 64.0;
+
+/// Plays back recorded frames against the wall clock, with pause, speed
+/// and seek.
+pub struct Player {
+    frames: Vec<Frame>,
+    /// Index of the next frame to emit.
+    pos: usize,
+    /// Playback position in recording time.
+    clock_ms: f64,
+    last_tick: Instant,
+    speed: f64,
+    paused: bool,
+}
+
+impl Player {
+    pub fn new(frames: Vec<Frame>) -> Self {
+        Self {
+            frames,
+            pos: 0,
+            clock_ms: 0.0,
+            last_tick: Instant::now(),
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    /// Advance the playback clock and return the frames that became due.
+    pub fn poll(&mut self) -> &[Frame] {
+        let now = Instant::now();
+        if !self.paused {
+            self.clock_ms += now.duration_since(self.last_tick).as_secs_f64() * 1000.0 * self.speed;
+            self.clock_ms = self.clock_ms.min(self.duration_ms() as f64);
+        }
+        self.last_tick = now;
+
+        let start = self.pos;
+        while self.pos < self.frames.len() && self.frames[self.pos].t_ms as f64 <= self.clock_ms {
+            self.pos += 1;
+        }
+        &self.frames[start..self.pos]
+    }
+
+    /// Jump by `delta_ms` (negative seeks backwards). Returns up to `history`
+    /// frames leading up to the new position so callers can rebuild graphs.
+    pub fn seek(&mut self, delta_ms: i64, history: usize) -> &[Frame] {
+        let target = (self.clock_ms + delta_ms as f64).clamp(0.0, self.duration_ms() as f64);
+        self.clock_ms = target;
+        self.pos = self.frames.partition_point(|f| f.t_ms as f64 <= target);
+        &self.frames[self.pos.saturating_sub(history)..self.pos]
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(MAX_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(MIN_SPEED);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn position_ms(&self) -> u64 {
+        self.clock_ms as u64
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.frames.last().map(|f| f.t_ms).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A recording path in the temp dir, removed on drop.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(test: &str) -> Self {
+            TempFile(std::env::temp_dir().join(format!("macy-rec-{}-{}.jsonl", test, std::process::id())))
+        }
+
+        fn write(test: &str, contents: &str) -> Self {
+            let file = Self::new(test);
+            std::fs::write(&file.0, contents).unwrap();
+            file
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn header_line(version: u32) -> String {
+        format!(
+            r#"{{"format":"{}","version":{},"started_at_ms":0,"interval_ms":500,"soc":{{"chip_name":"Apple M1"}}}}"#,
+            FORMAT_NAME, version
+        )
+    }
+
+    fn frame_line(t_ms: u64, cpu: f64) -> String {
+        format!(r#"{{"t_ms":{},"metrics":{{"cpu":{{"overall_percent":{}}}}}}}"#, t_ms, cpu)
+    }
+
+    fn frames_at(times: &[u64]) -> Vec<Frame> {
+        times
+            .iter()
+            .map(|&t_ms| Frame {
+                t_ms,
+                metrics: Metrics::default(),
+            })
+            .collect()
+    }
+
+    fn times(frames: &[Frame]) -> Vec<u64> {
+        frames.iter().map(|f| f.t_ms).collect()
+    }
+
+    #[test]
+    fn record_then_load_round_trips() {
+        let file = TempFile::new("round-trip");
+        let soc = SocInfo {
+            chip_name: "Apple M2".to_string(),
+            p_cores: 4,
+            ..SocInfo::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let (tee, writer) = record(&file.0, &soc, Duration::from_millis(250), rx).unwrap();
+        for cpu in [10.0, 20.0, 30.0] {
+            let mut metrics = Metrics::default();
+            metrics.cpu.overall_percent = cpu;
+            tx.send(metrics).unwrap();
+        }
+        drop(tx);
+        // The tee passes every sample on, and ends once they're all written
+        let passed: Vec<f64> = tee.iter().map(|m| m.cpu.overall_percent).collect();
+        assert_eq!(passed, [10.0, 20.0, 30.0]);
+        writer.join().unwrap().unwrap();
+
+        let recording = load(&file.0).unwrap();
+        assert_eq!(recording.soc.chip_name, "Apple M2");
+        assert_eq!(recording.soc.p_cores, 4);
+        assert_eq!(recording.interval, Duration::from_millis(250));
+        let cpu: Vec<f64> = recording.frames.iter().map(|f| f.metrics.cpu.overall_percent).collect();
+        assert_eq!(cpu, [10.0, 20.0, 30.0]);
+    }
+
+    /// Accepts `room` bytes, then fails like a full disk.
+    struct FullDisk {
+        room: usize,
+    }
+
+    impl Write for FullDisk {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.len() > self.room {
+                return Err(io::Error::other("no space left on device"));
+            }
+            self.room -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_errors_end_the_feed_and_are_reported() {
+        let (tx, rx) = mpsc::channel();
+        let (tee, writer) = spawn_writer(FullDisk { room: 0 }, PathBuf::from("out.macy"), rx);
+        tx.send(Metrics::default()).unwrap();
+
+        // The failed sample isn't passed on and the feed disconnects
+        let next = tee.recv_timeout(Duration::from_secs(5));
+        assert!(matches!(next, Err(mpsc::RecvTimeoutError::Disconnected)));
+        let err = writer.join().unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "writing out.macy: no space left on device");
+    }
+
+    #[test]
+    fn truncated_last_line_is_dropped() {
+        let contents = format!("{}\n{}\n{}\n{{\"t_ms\":1000,\"metr", header_line(1), frame_line(0, 5.0), frame_line(500, 6.0));
+        let file = TempFile::write("truncated", &contents);
+
+        let recording = load(&file.0).unwrap();
+        assert_eq!(times(&recording.frames), [0, 500]);
+    }
+
+    #[test]
+    fn bad_line_before_the_end_is_an_error() {
+        let contents = format!("{}\n{}\nnot json\n{}\n", header_line(1), frame_line(0, 5.0), frame_line(500, 6.0));
+        let file = TempFile::write("corrupt", &contents);
+
+        let err = load(&file.0).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line 3"), "{}", err);
+    }
+
+    #[test]
+    fn header_is_checked() {
+        let newer = TempFile::write("newer", &format!("{}\n", header_line(FORMAT_VERSION + 1)));
+        let err = load(&newer.0).err().unwrap();
+        assert!(err.to_string().contains("newer"), "{}", err);
+
+        let other = TempFile::write("other", "{\"format\":\"something-else\",\"version\":1,\"started_at_ms\":0,\"interval_ms\":1,\"soc\":{}}\n");
+        assert!(load(&other.0).is_err());
+
+        let empty = TempFile::write("empty", "");
+        assert!(load(&empty.0).is_err());
+
+        let older = TempFile::write("older", &format!("{}\n{}\n", header_line(1), frame_line(0, 1.0)));
+        assert_eq!(load(&older.0).unwrap().frames.len(), 1);
+    }
+
+    #[test]
+    fn poll_emits_frames_as_they_come_due() {
+        let mut player = Player::new(frames_at(&[0, 1000, 2000, 3000]));
+
+        assert_eq!(times(player.poll()), [0]);
+
+        // Pretend 1.5s of wall time went by
+        player.last_tick -= Duration::from_millis(1500);
+        assert_eq!(times(player.poll()), [1000]);
+
+        player.toggle_pause();
+        player.last_tick -= Duration::from_millis(5000);
+        assert!(player.poll().is_empty());
+        player.toggle_pause();
+
+        player.faster();
+        player.last_tick -= Duration::from_millis(1000);
+        assert_eq!(times(player.poll()), [2000, 3000]);
+
+        // The clock stops at the end of the recording
+        assert_eq!(player.position_ms(), 3000);
+    }
+
+    #[test]
+    fn seek_returns_history_up_to_the_new_position() {
+        let mut player = Player::new(frames_at(&[0, 1000, 2000, 3000, 4000]));
+
+        assert_eq!(times(player.seek(2500, 2)), [1000, 2000]);
+        assert_eq!(player.position_ms(), 2500);
+
+        assert_eq!(times(player.seek(-10_000, 2)), [0]);
+        assert_eq!(player.position_ms(), 0);
+
+        assert_eq!(times(player.seek(10_000, 10)), [0, 1000, 2000, 3000, 4000]);
+        assert_eq!(player.position_ms(), 4000);
+        assert!(player.poll().is_empty());
+    }
+
+    #[test]
+    fn speed_is_clamped() {
+        let mut player = Player::new(Vec::new());
+        for _ in 0..20 {
+            player.faster();
+        }
+        assert_eq!(player.speed(), MAX_SPEED);
+        for _ in 0..20 {
+            player.slower();
+        }
+        assert_eq!(player.speed(), MIN_SPEED);
+    }
+}
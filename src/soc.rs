@@ -1,15 +1,28 @@
+#[cfg(target_os = "macos")]
+use crate::chips;
+#[cfg(target_os = "macos")]
 use crate::sources::iokit;
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "macos")]
 use std::mem;
 
 /// Static information about the SoC.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SocInfo {
     pub chip_name: String,
     pub e_cores: u32,
     pub p_cores: u32,
     pub gpu_cores: u32,
     pub gpu_freqs: Vec<u32>,  // MHz, sorted ascending (P1..P15)
+    pub ecpu_freqs: Vec<u32>, // MHz, E-cluster DVFS states, ascending
+    pub pcpu_freqs: Vec<u32>, // MHz, P-cluster DVFS states, ascending
     pub total_memory_gb: f64,
+    /// Peak unified memory bandwidth from the chip table, 0 if unknown.
+    pub memory_bandwidth_gbs: f64,
+    /// The chip isn't in the chip table: everything above comes from the
+    /// system alone and may be missing.
+    pub unknown_chip: bool,
 }
 
 impl SocInfo {
@@ -21,19 +34,25 @@ impl SocInfo {
 
 impl std::fmt::Display for SocInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} ({}E+{}P CPU, {} GPU, {:.0}GB)",
-            self.chip_name,
-            self.e_cores,
-            self.p_cores,
-            self.gpu_cores,
-            self.total_memory_gb
-        )
+        write!(f, "{} (", self.chip_name)?;
+        if self.e_cores > 0 {
+            write!(f, "{}E+{}P CPU", self.e_cores, self.p_cores)?;
+        } else {
+            write!(f, "{} CPU", self.p_cores)?;
+        }
+        if self.gpu_cores > 0 {
+            write!(f, ", {} GPU", self.gpu_cores)?;
+        }
+        write!(f, ", {:.0}GB)", self.total_memory_gb)?;
+        if self.unknown_chip {
+            write!(f, " [unknown chip]")?;
+        }
+        Ok(())
     }
 }
 
 /// Read a sysctl value as a string.
+#[cfg(target_os = "macos")]
 fn sysctl_string(name: &str) -> Option<String> {
     let cname = std::ffi::CString::new(name).ok()?;
     let mut size: usize = 0;
@@ -59,6 +78,7 @@ fn sysctl_string(name: &str) -> Option<String> {
 }
 
 /// Read a sysctl value as u32.
+#[cfg(target_os = "macos")]
 fn sysctl_u32(name: &str) -> Option<u32> {
     let cname = std::ffi::CString::new(name).ok()?;
     let mut val: u32 = 0;
@@ -76,6 +96,7 @@ fn sysctl_u32(name: &str) -> Option<u32> {
 }
 
 /// Read a sysctl value as u64.
+#[cfg(target_os = "macos")]
 fn sysctl_u64(name: &str) -> Option<u64> {
     let cname = std::ffi::CString::new(name).ok()?;
     let mut val: u64 = 0;
@@ -92,8 +113,10 @@ fn sysctl_u64(name: &str) -> Option<u64> {
     }
 }
 
-/// Try to read GPU frequency table from IOKit, fall back to known tables.
-fn detect_gpu_freqs(chip_name: &str) -> Vec<u32> {
+/// Read the GPU frequency table from IOKit. Empty if no accelerator
+/// publishes it.
+#[cfg(target_os = "macos")]
+fn detect_gpu_freqs() -> Vec<u32> {
     // Try to get GPU frequencies from IOKit AGXAccelerator properties
     for class in &[
         "AGXAccelerator",
@@ -119,38 +142,59 @@ fn detect_gpu_freqs(chip_name: &str) -> Vec<u32> {
             }
         }
     }
+    Vec::new()
+}
 
-    // Fallback to known frequency tables by chip
-    if chip_name.contains("M4 Pro") || chip_name.contains("M4 Max") {
-        vec![444, 612, 808, 968, 1110, 1236, 1338, 1398]
-    } else if chip_name.contains("M4") {
-        vec![396, 528, 660, 792, 924, 1056, 1164, 1290, 1398]
-    } else if chip_name.contains("M3") {
-        vec![444, 612, 808, 968, 1110, 1236, 1338, 1398]
-    } else if chip_name.contains("M2") {
-        vec![396, 528, 660, 792, 924, 1056, 1164, 1290, 1398]
-    } else if chip_name.contains("M1") {
-        vec![396, 528, 660, 792, 924, 1056, 1164, 1278]
-    } else {
-        vec![396, 528, 660, 792, 924, 1056, 1164, 1290, 1398]
-    }
+/// Read the E- and P-cluster DVFS tables from the `pmgr` IOKit node.
+/// Entries are (frequency, voltage) u32 pairs; frequency is in Hz up to M3
+/// and in kHz from M4 on.
+#[cfg(target_os = "macos")]
+fn detect_cpu_freqs() -> (Vec<u32>, Vec<u32>) {
+    let read = |property: &str| -> Vec<u32> {
+        let Some(data) = iokit::get_iokit_data_property_by_name("pmgr", property) else {
+            return Vec::new();
+        };
+        let raw: Vec<u32> = data
+            .chunks_exact(8)
+            .map(|pair| u32::from_le_bytes([pair[0], pair[1], pair[2], pair[3]]))
+            .filter(|&f| f > 0)
+            .collect();
+        let divisor = if raw.iter().any(|&f| f > 100_000_000) { 1_000_000 } else { 1_000 };
+        raw.iter().map(|f| f / divisor).collect()
+    };
+
+    (read("voltage-states1-sram"), read("voltage-states5-sram"))
 }
 
-/// Detect the SoC info from the current system.
+/// Detect the SoC info from the current system. What the system reports
+/// wins (it knows about binned parts); the chip table fills in the gaps.
+/// Unknown chips get no made-up numbers, only what the system reports.
+#[cfg(target_os = "macos")]
 pub fn detect() -> SocInfo {
     let chip_name = sysctl_string("machdep.cpu.brand_string")
         .unwrap_or_else(|| "Unknown".to_string());
+    let spec = chips::lookup(&chip_name);
 
     // P-cores = perflevel0, E-cores = perflevel1
-    let p_cores = sysctl_u32("hw.perflevel0.logicalcpu").unwrap_or(4);
-    let e_cores = sysctl_u32("hw.perflevel1.logicalcpu").unwrap_or(6);
+    let p_cores = sysctl_u32("hw.perflevel0.logicalcpu")
+        .or(spec.as_ref().map(|s| s.p_cores))
+        .unwrap_or(0);
+    let e_cores = sysctl_u32("hw.perflevel1.logicalcpu")
+        .or(spec.as_ref().map(|s| s.e_cores))
+        .unwrap_or(0);
 
-    let gpu_cores = iokit::gpu_core_count().unwrap_or(10) as u32;
+    let gpu_cores = iokit::gpu_core_count()
+        .map(|count| count as u32)
+        .or_else(|| spec.as_ref()?.gpu_cores.last().copied())
+        .unwrap_or(0);
 
-    // Get GPU frequency table from IOKit (gpu-perf-state-freqs or similar)
-    let gpu_freqs = detect_gpu_freqs(&chip_name);
+    let mut gpu_freqs = detect_gpu_freqs();
+    if gpu_freqs.is_empty() {
+        gpu_freqs = spec.as_ref().map(|s| s.gpu_freqs.clone()).unwrap_or_default();
+    }
+    let (ecpu_freqs, pcpu_freqs) = detect_cpu_freqs();
 
-    let total_mem = sysctl_u64("hw.memsize").unwrap_or(16 * 1024 * 1024 * 1024);
+    let total_mem = sysctl_u64("hw.memsize").unwrap_or(0);
     let total_memory_gb = total_mem as f64 / (1024.0 * 1024.0 * 1024.0);
 
     SocInfo {
@@ -159,6 +203,45 @@ pub fn detect() -> SocInfo {
         p_cores,
         gpu_cores,
         gpu_freqs,
+        ecpu_freqs,
+        pcpu_freqs,
+        total_memory_gb,
+        memory_bandwidth_gbs: spec.as_ref().map_or(0.0, |s| s.memory_bandwidth_gbs),
+        unknown_chip: spec.is_none(),
+    }
+}
+
+/// Detect the CPU info from /proc/cpuinfo. Linux has no E/P split or GPU
+/// counters, so every core is reported as a P-core.
+#[cfg(target_os = "linux")]
+pub fn detect() -> SocInfo {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+
+    let chip_name = cpuinfo
+        .lines()
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "model name").then(|| value.trim().to_string())
+        })
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    let p_cores = cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("processor"))
+        .count() as u32;
+
+    let total_mem = crate::sources::memory::get_memory_info().total_bytes;
+    let total_memory_gb = total_mem as f64 / (1024.0 * 1024.0 * 1024.0);
+
+    SocInfo {
+        chip_name,
+        e_cores: 0,
+        p_cores,
+        gpu_cores: 0,
+        gpu_freqs: Vec::new(),
+        ecpu_freqs: Vec::new(),
+        pcpu_freqs: Vec::new(),
         total_memory_gb,
+        ..SocInfo::default()
     }
 }
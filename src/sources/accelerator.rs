@@ -0,0 +1,61 @@
+use core_foundation::base::TCFType;
+use std::io;
+
+use super::cf_utils::{cfdict_get_i64, cfdict_get_value};
+use super::iokit::get_iokit_properties;
+use super::{CVoidRef, MetricSource};
+use crate::metrics::Metrics;
+
+/// GPU utilization and memory from the accelerator's `PerformanceStatistics`
+/// registry property. Stateless: the driver keeps the values current.
+pub struct AcceleratorSource;
+
+/// A snapshot of the `PerformanceStatistics` dictionary.
+struct PerformanceStatistics {
+    device_utilization: i64,
+    renderer_utilization: i64,
+    tiler_utilization: i64,
+    in_use_memory: i64,
+    alloc_memory: i64,
+}
+
+impl MetricSource for AcceleratorSource {
+    fn describe(&self) -> &str {
+        "gpu (IOAccelerator PerformanceStatistics)"
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        read_performance_statistics()
+            .map(|_| ())
+            .ok_or_else(|| io::Error::other("no IOAccelerator PerformanceStatistics"))
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        let Some(stats) = read_performance_statistics() else {
+            return;
+        };
+        metrics.gpu.device_utilization = stats.device_utilization as f64;
+        metrics.gpu.renderer_utilization = stats.renderer_utilization as f64;
+        metrics.gpu.tiler_utilization = stats.tiler_utilization as f64;
+        metrics.gpu.in_use_memory_bytes = stats.in_use_memory.max(0) as u64;
+        metrics.gpu.alloc_memory_bytes = stats.alloc_memory.max(0) as u64;
+    }
+}
+
+fn read_performance_statistics() -> Option<PerformanceStatistics> {
+    let props = get_iokit_properties("IOAccelerator")?;
+    unsafe {
+        let stats = cfdict_get_value(props.as_concrete_TypeRef() as CVoidRef, "PerformanceStatistics");
+        if stats.is_null() {
+            return None;
+        }
+        let get = |key: &str| cfdict_get_i64(stats, key).unwrap_or(0);
+        Some(PerformanceStatistics {
+            device_utilization: get("Device Utilization %"),
+            renderer_utilization: get("Renderer Utilization %"),
+            tiler_utilization: get("Tiler Utilization %"),
+            in_use_memory: get("In use system memory"),
+            alloc_memory: get("Alloc system memory"),
+        })
+    }
+}
@@ -0,0 +1,383 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+use super::cf_utils::{cfdict_get_bool, cfdict_get_i64};
+#[cfg(target_os = "macos")]
+use super::iokit::get_iokit_properties;
+#[cfg(target_os = "macos")]
+use super::CVoidRef;
+use super::MetricSource;
+use crate::metrics::Metrics;
+#[cfg(target_os = "macos")]
+use core_foundation::base::TCFType;
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+
+/// Battery state. All zero (and `present` false) on machines without one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatteryInfo {
+    pub present: bool,
+    pub current_capacity_mah: f64,
+    /// Full charge capacity as the battery has aged.
+    pub max_capacity_mah: f64,
+    pub design_capacity_mah: f64,
+    pub cycle_count: u32,
+    pub voltage_v: f64,
+    /// Positive while charging, negative while discharging.
+    pub amperage_a: f64,
+    pub temperature_c: f64,
+    pub external_power: bool,
+}
+
+/// Where the battery is heading at the current rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeRemaining {
+    ToEmpty(Duration),
+    ToFull(Duration),
+}
+
+impl BatteryInfo {
+    pub fn charge_percent(&self) -> f64 {
+        if self.max_capacity_mah <= 0.0 {
+            return 0.0;
+        }
+        (self.current_capacity_mah / self.max_capacity_mah * 100.0).min(100.0)
+    }
+
+    /// Power flowing into (positive) or out of (negative) the battery.
+    pub fn watts(&self) -> f64 {
+        self.voltage_v * self.amperage_a
+    }
+
+    /// Full charge capacity relative to when the battery was new.
+    pub fn health_percent(&self) -> f64 {
+        if self.design_capacity_mah <= 0.0 {
+            return 0.0;
+        }
+        self.max_capacity_mah / self.design_capacity_mah * 100.0
+    }
+
+    /// Time to empty or full at `amperage_a`, usually an average over
+    /// several samples since the instantaneous current jumps around.
+    /// `None` when the battery is idle.
+    pub fn time_remaining(&self, amperage_a: f64) -> Option<TimeRemaining> {
+        // Below this the estimate runs into days and means nothing
+        const IDLE_AMPS: f64 = 0.01;
+
+        let hours = |mah: f64| Duration::from_secs_f64((mah / 1000.0 / amperage_a.abs() * 3600.0).max(0.0));
+        if amperage_a < -IDLE_AMPS {
+            Some(TimeRemaining::ToEmpty(hours(self.current_capacity_mah)))
+        } else if amperage_a > IDLE_AMPS {
+            Some(TimeRemaining::ToFull(hours(self.max_capacity_mah - self.current_capacity_mah)))
+        } else {
+            None
+        }
+    }
+}
+
+/// Battery source backed by `AppleSmartBattery` (macOS) or
+/// /sys/class/power_supply (Linux). Fails to initialize without a battery.
+pub struct BatterySource;
+
+impl MetricSource for BatterySource {
+    fn describe(&self) -> &str {
+        if cfg!(target_os = "linux") {
+            "battery (/sys/class/power_supply)"
+        } else {
+            "battery (AppleSmartBattery)"
+        }
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        read_battery()
+            .filter(|battery| battery.present)
+            .map(|_| ())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no battery"))
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        if let Some(battery) = read_battery() {
+            metrics.battery = battery;
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn read_battery() -> Option<BatteryInfo> {
+    let props = get_iokit_properties("AppleSmartBattery")?;
+    unsafe {
+        let dict = props.as_concrete_TypeRef() as CVoidRef;
+        let int = |key: &str| cfdict_get_i64(dict, key);
+        let flag = |key: &str| cfdict_get_bool(dict, key).unwrap_or(false);
+
+        // On Apple Silicon CurrentCapacity/MaxCapacity are percentages and
+        // the mAh values moved to the AppleRaw* keys
+        let current = int("AppleRawCurrentCapacity").or_else(|| int("CurrentCapacity"))?;
+        let max = int("AppleRawMaxCapacity").or_else(|| int("MaxCapacity"))?;
+
+        Some(BatteryInfo {
+            present: flag("BatteryInstalled"),
+            current_capacity_mah: current as f64,
+            max_capacity_mah: max as f64,
+            design_capacity_mah: int("DesignCapacity").unwrap_or(0) as f64,
+            cycle_count: int("CycleCount").unwrap_or(0).max(0) as u32,
+            voltage_v: int("Voltage").unwrap_or(0) as f64 / 1000.0,
+            amperage_a: int("Amperage").unwrap_or(0) as f64 / 1000.0,
+            // Hundredths of a degree
+            temperature_c: int("Temperature").unwrap_or(0) as f64 / 100.0,
+            external_power: flag("ExternalConnected"),
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+#[cfg(target_os = "linux")]
+fn read_battery() -> Option<BatteryInfo> {
+    read_power_supplies(Path::new(POWER_SUPPLY_DIR))
+}
+
+/// The system battery under `root`, with external power from any online
+/// mains supply. Peripherals (wireless mice and keyboards) report
+/// `scope == Device` and are skipped; of the rest the first by name wins,
+/// so BAT0 comes before BAT1 whatever order the directory lists them in.
+#[cfg(target_os = "linux")]
+fn read_power_supplies(root: &Path) -> Option<BatteryInfo> {
+    let mut supplies: Vec<PathBuf> = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect();
+    supplies.sort();
+
+    let battery_dir = supplies.iter().find(|dir| {
+        sysfs_string(dir, "type").as_deref() == Some("Battery")
+            && sysfs_string(dir, "scope").as_deref() != Some("Device")
+    })?;
+    let mut battery = read_sysfs_battery(battery_dir);
+    battery.external_power = supplies.iter().any(|dir| {
+        sysfs_string(dir, "type").as_deref() == Some("Mains") && sysfs_value(dir, "online") == Some(1.0)
+    });
+    Some(battery)
+}
+
+/// Read one `type == Battery` supply. Drivers report either charge (µAh)
+/// or energy (µWh); energy is converted at the current voltage.
+#[cfg(target_os = "linux")]
+fn read_sysfs_battery(dir: &Path) -> BatteryInfo {
+    let micro = |name: &str| sysfs_value(dir, name).map(|v| v / 1_000_000.0);
+
+    let voltage_v = micro("voltage_now")
+        .filter(|v| *v > 0.0)
+        .or_else(|| micro("voltage_min_design"))
+        .unwrap_or(0.0);
+    let mah = |charge: &str, energy: &str| {
+        micro(charge)
+            .map(|ah| ah * 1000.0)
+            .or_else(|| micro(energy).filter(|_| voltage_v > 0.0).map(|wh| wh / voltage_v * 1000.0))
+            .unwrap_or(0.0)
+    };
+
+    // current_now is unsigned on most drivers; the direction is in status
+    let amps = micro("current_now")
+        .or_else(|| micro("power_now").filter(|_| voltage_v > 0.0).map(|w| w / voltage_v))
+        .unwrap_or(0.0)
+        .abs();
+    let amperage_a = match sysfs_string(dir, "status").as_deref() {
+        Some("Discharging") => -amps,
+        Some("Charging") => amps,
+        _ => 0.0,
+    };
+
+    BatteryInfo {
+        present: sysfs_value(dir, "present") != Some(0.0),
+        current_capacity_mah: mah("charge_now", "energy_now"),
+        max_capacity_mah: mah("charge_full", "energy_full"),
+        design_capacity_mah: mah("charge_full_design", "energy_full_design"),
+        cycle_count: sysfs_value(dir, "cycle_count").unwrap_or(0.0) as u32,
+        voltage_v,
+        amperage_a,
+        // Tenths of a degree, where the driver has a sensor at all
+        temperature_c: sysfs_value(dir, "temp").unwrap_or(0.0) / 10.0,
+        external_power: false,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn sysfs_string(dir: &Path, name: &str) -> Option<String> {
+    Some(std::fs::read_to_string(dir.join(name)).ok()?.trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn sysfs_value(dir: &Path, name: &str) -> Option<f64> {
+    sysfs_string(dir, name)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(current_mah: f64, max_mah: f64) -> BatteryInfo {
+        BatteryInfo {
+            present: true,
+            current_capacity_mah: current_mah,
+            max_capacity_mah: max_mah,
+            design_capacity_mah: 5000.0,
+            ..BatteryInfo::default()
+        }
+    }
+
+    #[test]
+    fn charge_and_health() {
+        assert_eq!(battery(2000.0, 4000.0).charge_percent(), 50.0);
+        // Calibration can put the charge above the full capacity
+        assert_eq!(battery(4100.0, 4000.0).charge_percent(), 100.0);
+        assert_eq!(battery(0.0, 0.0).charge_percent(), 0.0);
+        assert_eq!(battery(2000.0, 4000.0).health_percent(), 80.0);
+        assert_eq!(BatteryInfo::default().health_percent(), 0.0);
+    }
+
+    #[test]
+    fn time_remaining_by_direction() {
+        let b = battery(2000.0, 4000.0);
+        assert_eq!(b.time_remaining(-1.0), Some(TimeRemaining::ToEmpty(Duration::from_secs(7200))));
+        assert_eq!(b.time_remaining(4.0), Some(TimeRemaining::ToFull(Duration::from_secs(1800))));
+        assert_eq!(b.time_remaining(0.0), None);
+        assert_eq!(b.time_remaining(-0.005), None);
+        // Charging past full doesn't go negative
+        assert_eq!(
+            battery(4100.0, 4000.0).time_remaining(1.0),
+            Some(TimeRemaining::ToFull(Duration::ZERO))
+        );
+    }
+
+    /// A throwaway /sys/class/power_supply tree, removed on drop.
+    #[cfg(target_os = "linux")]
+    struct FakePowerSupply(PathBuf);
+
+    #[cfg(target_os = "linux")]
+    impl FakePowerSupply {
+        fn new(test: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("macy-battery-{}-{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).unwrap();
+            FakePowerSupply(root)
+        }
+
+        fn supply(&self, name: &str, files: &[(&str, &str)]) {
+            let dir = self.0.join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            for (file, value) in files {
+                std::fs::write(dir.join(file), format!("{}\n", value)).unwrap();
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    impl Drop for FakePowerSupply {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn charge_based_driver() {
+        let tree = FakePowerSupply::new("charge");
+        tree.supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("present", "1"),
+                ("status", "Discharging"),
+                ("voltage_now", "12000000"),
+                ("current_now", "1500000"),
+                ("charge_now", "3000000"),
+                ("charge_full", "4000000"),
+                ("charge_full_design", "5000000"),
+                ("cycle_count", "123"),
+                ("temp", "315"),
+            ],
+        );
+        tree.supply("AC", &[("type", "Mains"), ("online", "1")]);
+
+        let b = read_power_supplies(&tree.0).unwrap();
+        assert!(b.present && b.external_power);
+        assert_eq!((b.current_capacity_mah, b.max_capacity_mah, b.design_capacity_mah), (3000.0, 4000.0, 5000.0));
+        assert_eq!((b.voltage_v, b.amperage_a), (12.0, -1.5));
+        assert_eq!((b.cycle_count, b.temperature_c), (123, 31.5));
+        assert_eq!(b.charge_percent(), 75.0);
+        assert_eq!(b.health_percent(), 80.0);
+        assert_eq!(b.time_remaining(b.amperage_a), Some(TimeRemaining::ToEmpty(Duration::from_secs(7200))));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn energy_based_driver() {
+        let tree = FakePowerSupply::new("energy");
+        // Energy at 10 V, with the rate as power and no current
+        tree.supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Charging"),
+                ("voltage_now", "10000000"),
+                ("power_now", "20000000"),
+                ("energy_now", "20000000"),
+                ("energy_full", "50000000"),
+                ("energy_full_design", "60000000"),
+            ],
+        );
+        tree.supply("AC", &[("type", "Mains"), ("online", "0")]);
+
+        let b = read_power_supplies(&tree.0).unwrap();
+        assert!(b.present && !b.external_power);
+        assert_eq!((b.current_capacity_mah, b.max_capacity_mah, b.design_capacity_mah), (2000.0, 5000.0, 6000.0));
+        assert_eq!(b.amperage_a, 2.0);
+        assert_eq!(b.watts(), 20.0);
+        assert_eq!(b.time_remaining(b.amperage_a), Some(TimeRemaining::ToFull(Duration::from_secs(5400))));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn idle_battery_has_no_current() {
+        let tree = FakePowerSupply::new("idle");
+        tree.supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Not charging"),
+                ("voltage_now", "12000000"),
+                ("current_now", "0"),
+                ("charge_now", "4000000"),
+                ("charge_full", "4000000"),
+            ],
+        );
+
+        let b = read_power_supplies(&tree.0).unwrap();
+        assert_eq!(b.amperage_a, 0.0);
+        assert_eq!(b.time_remaining(b.amperage_a), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn peripheral_batteries_are_skipped() {
+        let tree = FakePowerSupply::new("peripherals");
+        tree.supply(
+            "hidpp_battery_0",
+            &[("type", "Battery"), ("scope", "Device"), ("charge_now", "100"), ("charge_full", "200")],
+        );
+        tree.supply("BAT1", &[("type", "Battery"), ("charge_now", "1000000"), ("charge_full", "4000000")]);
+        tree.supply("BAT0", &[("type", "Battery"), ("charge_now", "3000000"), ("charge_full", "4000000")]);
+
+        let b = read_power_supplies(&tree.0).unwrap();
+        assert_eq!(b.current_capacity_mah, 3000.0);
+
+        let tree = FakePowerSupply::new("mouse-only");
+        tree.supply("hidpp_battery_0", &[("type", "Battery"), ("scope", "Device")]);
+        assert!(read_power_supplies(&tree.0).is_none());
+    }
+}
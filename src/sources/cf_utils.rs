@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use core_foundation::base::TCFType;
+use core_foundation::boolean::CFBoolean;
 use core_foundation::number::CFNumber;
 use core_foundation::string::CFString;
 
@@ -63,6 +64,15 @@ pub unsafe fn cfdict_get_i64(dict: CVoidRef, key: &str) -> Option<i64> {
     cfnum_to_i64(val)
 }
 
+/// Get a bool value from a CFDictionary by key.
+pub unsafe fn cfdict_get_bool(dict: CVoidRef, key: &str) -> Option<bool> {
+    let val = cfdict_get_value(dict, key);
+    if val.is_null() {
+        return None;
+    }
+    Some(CFBoolean::wrap_under_get_rule(val as *const _).into())
+}
+
 /// Get the count of a CFArray.
 pub unsafe fn cfarray_count(arr: CVoidRef) -> isize {
     if arr.is_null() {
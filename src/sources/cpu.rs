@@ -1,11 +1,18 @@
 #![allow(dead_code, deprecated)]
 
+#[cfg(target_os = "macos")]
 use libc::{
     c_int, host_processor_info, mach_host_self, mach_msg_type_number_t, natural_t,
     processor_info_array_t, PROCESSOR_CPU_LOAD_INFO,
 };
+use serde::{Deserialize, Serialize};
+use std::io;
+#[cfg(target_os = "macos")]
 use std::mem;
 
+use super::MetricSource;
+use crate::metrics::Metrics;
+
 /// Per-CPU tick data.
 #[derive(Debug, Clone, Default)]
 struct CpuTicks {
@@ -30,7 +37,8 @@ pub struct CpuTracker {
     prev: Vec<CpuTicks>,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CpuUsage {
     pub overall_percent: f64,
     pub per_core: Vec<f64>,
@@ -38,8 +46,7 @@ pub struct CpuUsage {
 
 impl CpuTracker {
     pub fn new() -> Self {
-        let ticks = read_cpu_ticks();
-        Self { prev: ticks }
+        Self { prev: Vec::new() }
     }
 
     /// Sample current CPU ticks and compute usage since last call.
@@ -80,7 +87,30 @@ impl CpuTracker {
     }
 }
 
+impl MetricSource for CpuTracker {
+    fn describe(&self) -> &str {
+        if cfg!(target_os = "linux") {
+            "cpu (/proc/stat)"
+        } else {
+            "cpu (host_processor_info)"
+        }
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        self.prev = read_cpu_ticks();
+        if self.prev.is_empty() {
+            return Err(io::Error::other("no per-CPU tick counters"));
+        }
+        Ok(())
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        metrics.cpu = CpuTracker::sample(self);
+    }
+}
+
 /// Read per-CPU tick counts via host_processor_info.
+#[cfg(target_os = "macos")]
 fn read_cpu_ticks() -> Vec<CpuTicks> {
     unsafe {
         let mut num_cpus: natural_t = 0;
@@ -129,3 +159,80 @@ fn read_cpu_ticks() -> Vec<CpuTicks> {
         ticks
     }
 }
+
+/// Read per-CPU tick counts from /proc/stat.
+#[cfg(target_os = "linux")]
+fn read_cpu_ticks() -> Vec<CpuTicks> {
+    std::fs::read_to_string("/proc/stat")
+        .map(|text| parse_proc_stat(&text))
+        .unwrap_or_default()
+}
+
+/// Parse the per-core `cpuN` lines of /proc/stat.
+/// Fields are: user nice system idle iowait irq softirq steal ...
+#[cfg(target_os = "linux")]
+fn parse_proc_stat(text: &str) -> Vec<CpuTicks> {
+    text.lines()
+        .filter(|line| {
+            line.strip_prefix("cpu")
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        })
+        .map(|line| {
+            let f: Vec<u64> = line
+                .split_whitespace()
+                .skip(1)
+                .map(|v| v.parse().unwrap_or(0))
+                .collect();
+            let field = |i: usize| f.get(i).copied().unwrap_or(0);
+            CpuTicks {
+                user: field(0),
+                nice: field(1),
+                // irq, softirq and steal count as time the core wasn't ours
+                system: field(2) + field(5) + field(6) + field(7),
+                // iowait is idle time from the core's point of view
+                idle: field(3) + field(4),
+            }
+        })
+        .collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    const PROC_STAT: &str = "\
+cpu  4705 356 584 3699 23 23 0 0 0 0
+cpu0 1393 280 302 1705 3 7 11 13 0 0
+cpu1 3312 76 282 1994 20 16 0 0 0 0
+intr 1462898 0 0 0
+ctxt 115315
+btime 1700000000
+processes 1234
+procs_running 2
+";
+
+    #[test]
+    fn per_core_lines_only() {
+        // The aggregate "cpu" line and the other counters are skipped
+        assert_eq!(parse_proc_stat(PROC_STAT).len(), 2);
+    }
+
+    #[test]
+    fn irq_and_steal_are_system_and_iowait_is_idle() {
+        let ticks = parse_proc_stat(PROC_STAT);
+        let cpu0 = &ticks[0];
+        assert_eq!((cpu0.user, cpu0.nice), (1393, 280));
+        assert_eq!(cpu0.system, 302 + 7 + 11 + 13);
+        assert_eq!(cpu0.idle, 1705 + 3);
+        assert_eq!(cpu0.active(), 1393 + 280 + 333);
+        assert_eq!(cpu0.total(), cpu0.active() + 1708);
+    }
+
+    #[test]
+    fn short_lines_from_old_kernels() {
+        // Before 2.6.11 there was no steal field, before 2.5.41 only four
+        let ticks = parse_proc_stat("cpu0 10 20 30 40\ncpu1 1 2 3 4 5 6 7\n");
+        assert_eq!((ticks[0].system, ticks[0].idle), (30, 40));
+        assert_eq!((ticks[1].system, ticks[1].idle), (3 + 6 + 7, 4 + 5));
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::time::Instant;
+
+use super::MetricSource;
+use crate::metrics::Metrics;
+
+/// I/O on one disk since the previous sample.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiskDevice {
+    pub name: String,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    pub read_iops: f64,
+    pub write_iops: f64,
+    /// Average time per operation, 0 when idle.
+    pub latency_ms: f64,
+}
+
+/// Cumulative counters, turned into rates between samples.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+    bytes_read: u64,
+    bytes_written: u64,
+    reads: u64,
+    writes: u64,
+    /// Read plus write time, in nanoseconds.
+    io_time_ns: u64,
+}
+
+/// Disk source backed by `IOBlockStorageDriver` statistics (macOS) or
+/// /proc/diskstats (Linux). Keeps the previous counters per device.
+pub struct DiskSource {
+    prev: HashMap<String, Counters>,
+    prev_time: Option<Instant>,
+}
+
+impl DiskSource {
+    pub fn new() -> Self {
+        Self {
+            prev: HashMap::new(),
+            prev_time: None,
+        }
+    }
+}
+
+impl MetricSource for DiskSource {
+    fn describe(&self) -> &str {
+        if cfg!(target_os = "linux") {
+            "disk (/proc/diskstats)"
+        } else {
+            "disk (IOBlockStorageDriver)"
+        }
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        let disks = read_disks()?;
+        if disks.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no disks"));
+        }
+        self.prev = disks.into_iter().collect();
+        self.prev_time = Some(Instant::now());
+        Ok(())
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        let Ok(disks) = read_disks() else {
+            return;
+        };
+        let now = Instant::now();
+        let secs = self
+            .prev_time
+            .map_or(0.0, |prev| now.duration_since(prev).as_secs_f64());
+
+        let mut devices = Vec::with_capacity(disks.len());
+        for (name, curr) in &disks {
+            let mut device = DiskDevice {
+                name: name.clone(),
+                ..DiskDevice::default()
+            };
+            // Disks that just appeared get their rates next time
+            if let (Some(prev), true) = (self.prev.get(name), secs > 0.0) {
+                let rate = |prev: u64, curr: u64| curr.saturating_sub(prev) as f64 / secs;
+                device.read_bytes_per_sec = rate(prev.bytes_read, curr.bytes_read);
+                device.write_bytes_per_sec = rate(prev.bytes_written, curr.bytes_written);
+                device.read_iops = rate(prev.reads, curr.reads);
+                device.write_iops = rate(prev.writes, curr.writes);
+
+                let ops = (curr.reads + curr.writes).saturating_sub(prev.reads + prev.writes);
+                if ops > 0 {
+                    let io_time_ns = curr.io_time_ns.saturating_sub(prev.io_time_ns);
+                    device.latency_ms = io_time_ns as f64 / ops as f64 / 1e6;
+                }
+            }
+            devices.push(device);
+        }
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+
+        self.prev = disks.into_iter().collect();
+        self.prev_time = Some(now);
+        metrics.disks = devices;
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn read_disks() -> io::Result<Vec<(String, Counters)>> {
+    Ok(super::iokit::block_storage_stats()
+        .into_iter()
+        .map(|stats| {
+            let counters = Counters {
+                bytes_read: stats.bytes_read,
+                bytes_written: stats.bytes_written,
+                reads: stats.reads,
+                writes: stats.writes,
+                io_time_ns: stats.read_time_ns + stats.write_time_ns,
+            };
+            (stats.name, counters)
+        })
+        .collect())
+}
+
+/// Whole disks from /proc/diskstats. Partitions and virtual devices (loop,
+/// ram, zram) are left out; whole disks are the ones in /sys/block that
+/// aren't virtual.
+#[cfg(target_os = "linux")]
+fn read_disks() -> io::Result<Vec<(String, Counters)>> {
+    let text = std::fs::read_to_string("/proc/diskstats")?;
+    Ok(parse_diskstats(&text)
+        .into_iter()
+        .filter(|(name, _)| {
+            let device = std::path::Path::new("/sys/block").join(name);
+            device.exists()
+                && !std::fs::canonicalize(&device)
+                    .is_ok_and(|path| path.starts_with("/sys/devices/virtual"))
+        })
+        .collect())
+}
+
+/// Parse /proc/diskstats: major, minor, name, then reads, reads merged,
+/// sectors read, ms reading, writes, writes merged, sectors written, ms
+/// writing. Sectors are always 512 bytes here, whatever the hardware uses.
+#[cfg(target_os = "linux")]
+fn parse_diskstats(text: &str) -> Vec<(String, Counters)> {
+    const SECTOR_BYTES: u64 = 512;
+
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 11 {
+                return None;
+            }
+            let num = |i: usize| fields[i].parse::<u64>().unwrap_or(0);
+            Some((
+                fields[2].to_string(),
+                Counters {
+                    reads: num(3),
+                    bytes_read: num(5) * SECTOR_BYTES,
+                    writes: num(7),
+                    bytes_written: num(9) * SECTOR_BYTES,
+                    io_time_ns: (num(6) + num(10)) * 1_000_000,
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diskstats_sectors_and_milliseconds() {
+        // A kernel 5.x line: 14 fields, then discard and flush fields
+        let text = "\
+ 259       0 nvme0n1 218471 70845 13462314 53612 412563 311026 26854080 298734 0 203348 367285 0 0 0 0 19571 14938
+ 259       1 nvme0n1p1 412 0 12274 89 2 0 2 0 0 120 89 0 0 0 0 0 0
+   7       0 loop0 56 0 2208 21 0 0 0 0 0 64 21
+   8       0 sda 1 2 3
+";
+        let disks = parse_diskstats(text);
+        let names: Vec<&str> = disks.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["nvme0n1", "nvme0n1p1", "loop0"]);
+
+        let nvme = disks[0].1;
+        assert_eq!((nvme.reads, nvme.writes), (218471, 412563));
+        assert_eq!(nvme.bytes_read, 13462314 * 512);
+        assert_eq!(nvme.bytes_written, 26854080 * 512);
+        assert_eq!(nvme.io_time_ns, (53612 + 298734) * 1_000_000);
+    }
+}
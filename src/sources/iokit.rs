@@ -4,9 +4,15 @@ use core_foundation::base::TCFType;
 use core_foundation::dictionary::CFMutableDictionary;
 use core_foundation::number::CFNumber;
 use core_foundation::string::CFString;
-use std::ffi::c_void;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr};
+use std::io;
 
-type IOReturn = i32;
+use super::cf_utils::{cfarray_count, cfarray_get, cfdict_get_i64, cfdict_get_value, from_cfstring};
+use super::process::parse_client_creator;
+use super::CVoidRef;
+
+pub type IOReturn = i32;
 type MachPort = u32;
 
 #[allow(non_upper_case_globals)]
@@ -14,7 +20,13 @@ const kIOMainPortDefault: MachPort = 0;
 
 extern "C" {
     fn IOServiceMatching(name: *const i8) -> *mut c_void;
+    fn IOServiceNameMatching(name: *const i8) -> *mut c_void;
     fn IOServiceGetMatchingService(mainPort: MachPort, matching: *mut c_void) -> u32;
+    fn IOServiceGetMatchingServices(
+        mainPort: MachPort,
+        matching: *mut c_void,
+        iterator: *mut u32,
+    ) -> IOReturn;
     fn IORegistryEntryCreateCFProperty(
         entry: u32,
         key: *const c_void,
@@ -22,6 +34,180 @@ extern "C" {
         options: u32,
     ) -> *const c_void;
     fn IOObjectRelease(object: u32) -> IOReturn;
+    fn IOObjectGetClass(object: u32, class_name: *mut c_char) -> IOReturn;
+    fn IORegistryEntryGetChildIterator(
+        entry: u32,
+        plane: *const c_char,
+        iterator: *mut u32,
+    ) -> IOReturn;
+    fn IOIteratorNext(iterator: u32) -> u32;
+    fn IORegistryEntryCreateCFProperties(
+        entry: u32,
+        properties: *mut *const c_void,
+        allocator: *const c_void,
+        options: u32,
+    ) -> IOReturn;
+    fn IOServiceOpen(service: u32, owning_task: MachPort, kind: u32, connect: *mut u32) -> IOReturn;
+    fn IOServiceClose(connect: u32) -> IOReturn;
+    fn IOConnectCallStructMethod(
+        connect: u32,
+        selector: u32,
+        input: *const c_void,
+        input_size: usize,
+        output: *mut c_void,
+        output_size: *mut usize,
+    ) -> IOReturn;
+}
+
+/// An IOKit registry entry, released on drop.
+pub struct RegistryEntry(u32);
+
+impl RegistryEntry {
+    /// The first service of a class (or a subclass of it).
+    pub fn matching(class_name: &str) -> Option<Self> {
+        let class_cstr = std::ffi::CString::new(class_name).ok()?;
+        unsafe {
+            let matching = IOServiceMatching(class_cstr.as_ptr());
+            if matching.is_null() {
+                return None;
+            }
+            let service = IOServiceGetMatchingService(kIOMainPortDefault, matching);
+            (service != 0).then_some(RegistryEntry(service))
+        }
+    }
+
+    /// Every service of a class (or a subclass of it).
+    pub fn all_matching(class_name: &str) -> Entries {
+        let Ok(class_cstr) = std::ffi::CString::new(class_name) else {
+            return Entries(0);
+        };
+        let mut iterator = 0;
+        unsafe {
+            let matching = IOServiceMatching(class_cstr.as_ptr());
+            // Consumes the matching dictionary, even on failure
+            if matching.is_null()
+                || IOServiceGetMatchingServices(kIOMainPortDefault, matching, &mut iterator) != 0
+            {
+                iterator = 0;
+            }
+        }
+        Entries(iterator)
+    }
+
+    /// The entry's children in the IOService plane.
+    pub fn children(&self) -> Entries {
+        let mut iterator = 0;
+        unsafe {
+            if IORegistryEntryGetChildIterator(self.0, c"IOService".as_ptr(), &mut iterator) != 0 {
+                iterator = 0;
+            }
+        }
+        Entries(iterator)
+    }
+
+    /// The entry's IOKit class, e.g. "AGXDeviceUserClient".
+    pub fn class_name(&self) -> Option<String> {
+        // io_name_t is a 128-byte C string
+        let mut buf = [0 as c_char; 128];
+        unsafe {
+            if IOObjectGetClass(self.0, buf.as_mut_ptr()) != 0 {
+                return None;
+            }
+            Some(CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned())
+        }
+    }
+
+    /// Open a user-client connection to the service.
+    pub fn open(&self) -> io::Result<Connection> {
+        let mut connect = 0;
+        #[allow(deprecated)]
+        let task = unsafe { libc::mach_task_self() };
+        let kr = unsafe { IOServiceOpen(self.0, task, 0, &mut connect) };
+        if kr != 0 {
+            return Err(io::Error::other(format!("IOServiceOpen failed: {:#x}", kr)));
+        }
+        Ok(Connection(connect))
+    }
+
+    /// All of the entry's properties.
+    pub fn properties(&self) -> Option<CFMutableDictionary> {
+        let mut props: *const c_void = std::ptr::null();
+        unsafe {
+            let kr = IORegistryEntryCreateCFProperties(self.0, &mut props, std::ptr::null(), 0);
+            if kr != 0 || props.is_null() {
+                return None;
+            }
+            Some(CFMutableDictionary::wrap_under_create_rule(props as *mut _))
+        }
+    }
+}
+
+impl Drop for RegistryEntry {
+    fn drop(&mut self) {
+        unsafe {
+            IOObjectRelease(self.0);
+        }
+    }
+}
+
+/// An open connection to a service's user client, closed on drop.
+pub struct Connection(u32);
+
+impl Connection {
+    /// Call a struct-in, struct-out external method of the user client.
+    ///
+    /// # Safety
+    /// `I` and `O` must match the layouts the driver expects for `selector`.
+    pub unsafe fn call_struct_method<I, O>(
+        &self,
+        selector: u32,
+        input: &I,
+        output: &mut O,
+    ) -> IOReturn {
+        let mut output_size = std::mem::size_of::<O>();
+        IOConnectCallStructMethod(
+            self.0,
+            selector,
+            input as *const I as *const c_void,
+            std::mem::size_of::<I>(),
+            output as *mut O as *mut c_void,
+            &mut output_size,
+        )
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            IOServiceClose(self.0);
+        }
+    }
+}
+
+/// Iterator over registry entries from an IOKit iterator, e.g. an entry's
+/// children or all services of a class.
+pub struct Entries(u32);
+
+impl Iterator for Entries {
+    type Item = RegistryEntry;
+
+    fn next(&mut self) -> Option<RegistryEntry> {
+        if self.0 == 0 {
+            return None;
+        }
+        let entry = unsafe { IOIteratorNext(self.0) };
+        (entry != 0).then_some(RegistryEntry(entry))
+    }
+}
+
+impl Drop for Entries {
+    fn drop(&mut self) {
+        if self.0 != 0 {
+            unsafe {
+                IOObjectRelease(self.0);
+            }
+        }
+    }
 }
 
 /// Get an integer property from an IOKit registry entry matched by class name.
@@ -56,7 +242,23 @@ pub fn get_iokit_int_property(class_name: &str, property: &str) -> Option<i64> {
 pub fn get_iokit_data_property(class_name: &str, property: &str) -> Option<Vec<u8>> {
     unsafe {
         let class_cstr = std::ffi::CString::new(class_name).ok()?;
-        let matching = IOServiceMatching(class_cstr.as_ptr());
+        service_data_property(IOServiceMatching(class_cstr.as_ptr()), property)
+    }
+}
+
+/// Get a Data property as raw bytes from a service matched by its registry
+/// name (e.g. "pmgr") rather than its class.
+pub fn get_iokit_data_property_by_name(service_name: &str, property: &str) -> Option<Vec<u8>> {
+    unsafe {
+        let name_cstr = std::ffi::CString::new(service_name).ok()?;
+        service_data_property(IOServiceNameMatching(name_cstr.as_ptr()), property)
+    }
+}
+
+/// Read a Data property from the first service matching `matching`.
+/// Consumes the matching dictionary.
+fn service_data_property(matching: *mut c_void, property: &str) -> Option<Vec<u8>> {
+    unsafe {
         if matching.is_null() {
             return None;
         }
@@ -110,30 +312,106 @@ pub fn gpu_core_count() -> Option<i64> {
 
 /// Get all IOKit properties for a service as a CFDictionary.
 pub fn get_iokit_properties(class_name: &str) -> Option<CFMutableDictionary> {
-    unsafe {
-        let class_cstr = std::ffi::CString::new(class_name).ok()?;
-        let matching = IOServiceMatching(class_cstr.as_ptr());
-        if matching.is_null() {
-            return None;
-        }
-        let service = IOServiceGetMatchingService(kIOMainPortDefault, matching);
-        if service == 0 {
-            return None;
+    RegistryEntry::matching(class_name)?.properties()
+}
+
+/// A process with an open GPU connection.
+pub struct GpuClient {
+    pub pid: u32,
+    pub name: String,
+    /// GPU time used so far, in nanoseconds.
+    pub gpu_time_ns: u64,
+}
+
+/// Accumulated GPU time per process, from the `AGXDeviceUserClient`
+/// children of the GPU accelerator. A process can hold several clients
+/// (one per Metal device/queue), so their times are summed.
+pub fn gpu_clients() -> Vec<GpuClient> {
+    let Some(accelerator) = RegistryEntry::matching("IOAccelerator") else {
+        return Vec::new();
+    };
+
+    let mut clients: HashMap<u32, GpuClient> = HashMap::new();
+    for child in accelerator.children() {
+        if child.class_name().as_deref() != Some("AGXDeviceUserClient") {
+            continue;
         }
-        extern "C" {
-            fn IORegistryEntryCreateCFProperties(
-                entry: u32,
-                properties: *mut *const c_void,
-                allocator: *const c_void,
-                options: u32,
-            ) -> IOReturn;
+        let Some(props) = child.properties() else {
+            continue;
+        };
+        let dict = props.as_concrete_TypeRef() as CVoidRef;
+        unsafe {
+            // "pid 123, WindowServer"
+            let Some(creator) = from_cfstring(cfdict_get_value(dict, "IOUserClientCreator")) else {
+                continue;
+            };
+            let Some((pid, name)) = parse_client_creator(&creator) else {
+                continue;
+            };
+
+            let usage = cfdict_get_value(dict, "AppUsage");
+            let mut gpu_time_ns = 0;
+            for i in 0..cfarray_count(usage) {
+                let app = cfarray_get(usage, i);
+                gpu_time_ns += cfdict_get_i64(app, "accumulatedGPUTime").unwrap_or(0).max(0) as u64;
+            }
+
+            clients
+                .entry(pid)
+                .or_insert_with(|| GpuClient {
+                    pid,
+                    name: name.to_string(),
+                    gpu_time_ns: 0,
+                })
+                .gpu_time_ns += gpu_time_ns;
         }
-        let mut props: *const c_void = std::ptr::null();
-        let kr = IORegistryEntryCreateCFProperties(service, &mut props, std::ptr::null(), 0);
-        IOObjectRelease(service);
-        if kr != 0 || props.is_null() {
-            return None;
+    }
+    clients.into_values().collect()
+}
+
+/// Cumulative I/O counters of one block storage driver.
+pub struct BlockStorageStats {
+    /// BSD name of the whole-disk media, e.g. "disk0".
+    pub name: String,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub reads: u64,
+    pub writes: u64,
+    /// Time spent on reads and writes, in nanoseconds.
+    pub read_time_ns: u64,
+    pub write_time_ns: u64,
+}
+
+/// The `Statistics` dictionary of every `IOBlockStorageDriver`, named after
+/// the `IOMedia` child that carries the BSD name.
+pub fn block_storage_stats() -> Vec<BlockStorageStats> {
+    let mut drives = Vec::new();
+    for driver in RegistryEntry::all_matching("IOBlockStorageDriver") {
+        let Some(name) = driver.children().find_map(|child| {
+            let props = child.properties()?;
+            unsafe { from_cfstring(cfdict_get_value(props.as_concrete_TypeRef() as CVoidRef, "BSD Name")) }
+        }) else {
+            continue;
+        };
+        let Some(props) = driver.properties() else {
+            continue;
+        };
+        unsafe {
+            let stats = cfdict_get_value(props.as_concrete_TypeRef() as CVoidRef, "Statistics");
+            if stats.is_null() {
+                continue;
+            }
+            let get = |key: &str| cfdict_get_i64(stats, key).unwrap_or(0).max(0) as u64;
+            drives.push(BlockStorageStats {
+                name,
+                bytes_read: get("Bytes (Read)"),
+                bytes_written: get("Bytes (Write)"),
+                reads: get("Operations (Read)"),
+                writes: get("Operations (Write)"),
+                read_time_ns: get("Total Time (Read)"),
+                write_time_ns: get("Total Time (Write)"),
+            });
         }
-        Some(CFMutableDictionary::wrap_under_create_rule(props as *mut _))
     }
+    drives
 }
@@ -1,10 +1,18 @@
 #![allow(dead_code)]
 
-use super::cf_utils::*;
-use super::CVoidRef;
+use super::{
+    parse_cpu_clusters, parse_gpu_residency, parse_gpu_stats, parse_power, Channel, IOReportDelta,
+    State,
+};
+use crate::metrics::Metrics;
+use crate::soc::SocInfo;
+use crate::sources::cf_utils::*;
+use crate::sources::{CVoidRef, MetricSource};
 use core_foundation::base::TCFType;
 use core_foundation::string::CFString;
 use std::ffi::c_void;
+use std::io;
+use std::time::Instant;
 
 // IOReport FFI - links against libIOReport.dylib (private framework, available on all macOS)
 #[link(name = "IOReport")]
@@ -49,6 +57,7 @@ extern "C" {
     fn IOReportChannelGetSubGroup(channel: CVoidRef) -> CVoidRef;
     fn IOReportChannelGetChannelName(channel: CVoidRef) -> CVoidRef;
     fn IOReportChannelGetUnitLabel(channel: CVoidRef) -> CVoidRef;
+    fn IOReportChannelGetFormat(channel: CVoidRef) -> u8;
     fn IOReportStateGetCount(channel: CVoidRef) -> i32;
     fn IOReportStateGetNameForIndex(channel: CVoidRef, idx: i32) -> CVoidRef;
     fn IOReportStateGetResidency(channel: CVoidRef, idx: i32) -> i64;
@@ -68,27 +77,16 @@ extern "C" {
 // kCFAllocatorDefault
 const CF_ALLOCATOR_DEFAULT: CVoidRef = std::ptr::null();
 
+// IOReportChannelGetFormat values
+const FORMAT_SIMPLE: u8 = 1;
+const FORMAT_STATE: u8 = 2;
+
 /// Subscription handle for IOReport sampling.
 pub struct IOReportSubscription {
     subscription: CVoidRef,
     channels: CVoidRef, // CFMutableDictionaryRef
 }
 
-/// Parsed GPU metrics from IOReport delta.
-#[derive(Debug, Clone, Default)]
-pub struct GpuMetrics {
-    pub utilization: f64,
-    pub freq_mhz: f64,
-    pub power_watts: f64,
-}
-
-/// Parsed CPU power from IOReport delta.
-#[derive(Debug, Clone, Default)]
-pub struct PowerMetrics {
-    pub cpu_watts: f64,
-    pub gpu_watts: f64,
-}
-
 /// Get the channels array from a sample/delta CFDictionary.
 unsafe fn get_channels_array(dict: CVoidRef) -> CVoidRef {
     cfdict_get_value(dict, "IOReportChannels")
@@ -119,8 +117,8 @@ pub fn create_subscription(groups: &[&str]) -> Option<IOReportSubscription> {
 
         // Merge all channel dicts into the first one
         let merged = channel_dicts[0];
-        for i in 1..channel_dicts.len() {
-            IOReportMergeChannels(merged, channel_dicts[i], std::ptr::null());
+        for dict in &channel_dicts[1..] {
+            IOReportMergeChannels(merged, *dict, std::ptr::null());
         }
 
         // Create a mutable copy for the subscription
@@ -174,143 +172,123 @@ pub fn release_sample(sample: CVoidRef) {
     }
 }
 
-/// Parse GPU utilization and frequency from a delta sample.
-/// Uses GPUPH channel from "GPU Stats"/"GPU Performance States".
-/// States are: OFF, P1, P2, ... P15 where P-states map to frequency levels.
-/// gpu_freqs should be sorted ascending (lowest freq first) matching P1, P2, ...
-pub fn parse_gpu_stats(delta: CVoidRef, gpu_freqs: &[u32]) -> (f64, f64) {
-    unsafe {
-        let items = get_channels_array(delta);
-        if items.is_null() {
-            return (0.0, 0.0);
-        }
-
-        let count = cfarray_count(items);
-        let mut total_active: i64 = 0;
-        let mut total: i64 = 0;
-        let mut weighted_freq: f64 = 0.0;
-
-        for i in 0..count {
-            let ch = cfarray_get(items, i);
-            if ch.is_null() {
-                continue;
-            }
-
-            let group = from_cfstring(IOReportChannelGetGroup(ch));
-            let subgroup = from_cfstring(IOReportChannelGetSubGroup(ch));
-            let channel_name = from_cfstring(IOReportChannelGetChannelName(ch));
-
-            let is_gpuph = group.as_deref() == Some("GPU Stats")
-                && subgroup.as_deref() == Some("GPU Performance States")
-                && channel_name.as_deref() == Some("GPUPH");
-
-            if !is_gpuph {
-                continue;
-            }
-
-            let state_count = IOReportStateGetCount(ch);
-            for s in 0..state_count {
-                let name = from_cfstring(IOReportStateGetNameForIndex(ch, s))
-                    .unwrap_or_default();
-                let residency = IOReportStateGetResidency(ch, s);
-
-                total += residency;
-
-                // OFF state = GPU is completely off
-                if name == "OFF" {
+impl IOReportDelta {
+    /// Copy the channels of a raw delta sample into an owned model.
+    pub fn from_cf(delta: CVoidRef) -> Self {
+        unsafe {
+            let items = get_channels_array(delta);
+            let count = cfarray_count(items);
+            let mut channels = Vec::with_capacity(count.max(0) as usize);
+
+            for i in 0..count {
+                let ch = cfarray_get(items, i);
+                if ch.is_null() {
                     continue;
                 }
 
-                // Active P-states: P1, P2, ..., P15
-                // P-state index maps to gpu_freqs array (P1 -> freqs[0], P2 -> freqs[1], etc.)
-                total_active += residency;
-
-                if let Some(idx) = name.strip_prefix('P').and_then(|n| n.parse::<usize>().ok()) {
-                    if idx > 0 && idx <= gpu_freqs.len() {
-                        weighted_freq += gpu_freqs[idx - 1] as f64 * residency as f64;
+                let mut channel = Channel {
+                    group: from_cfstring(IOReportChannelGetGroup(ch)).unwrap_or_default(),
+                    subgroup: from_cfstring(IOReportChannelGetSubGroup(ch)).unwrap_or_default(),
+                    name: from_cfstring(IOReportChannelGetChannelName(ch)).unwrap_or_default(),
+                    unit: from_cfstring(IOReportChannelGetUnitLabel(ch)).unwrap_or_default(),
+                    ..Channel::default()
+                };
+
+                match IOReportChannelGetFormat(ch) {
+                    FORMAT_SIMPLE => channel.value = IOReportSimpleGetIntegerValue(ch, 0),
+                    FORMAT_STATE => {
+                        let state_count = IOReportStateGetCount(ch);
+                        for s in 0..state_count {
+                            channel.states.push(State {
+                                name: from_cfstring(IOReportStateGetNameForIndex(ch, s))
+                                    .unwrap_or_default(),
+                                residency: IOReportStateGetResidency(ch, s),
+                            });
+                        }
                     }
+                    _ => {}
                 }
-            }
-        }
 
-        let utilization = if total > 0 {
-            (total_active as f64 / total as f64) * 100.0
-        } else {
-            0.0
-        };
-
-        let avg_freq = if total_active > 0 {
-            weighted_freq / total_active as f64
-        } else {
-            0.0
-        };
+                channels.push(channel);
+            }
 
-        (utilization, avg_freq)
+            IOReportDelta { channels }
+        }
     }
 }
 
-/// Convert energy value to joules based on unit string.
-fn energy_to_joules(value: i64, unit: &str) -> f64 {
-    match unit {
-        "nJ" => value as f64 / 1_000_000_000.0,
-        "uJ" => value as f64 / 1_000_000.0,
-        "mJ" => value as f64 / 1_000.0,
-        _ => value as f64 / 1_000_000_000.0, // default to nJ
-    }
+/// CPU/GPU frequency and energy readings from IOReport.
+///
+/// Keeps the previous raw sample so each call reports the delta since the
+/// last one.
+pub struct IOReportSource {
+    gpu_freqs: Vec<u32>,
+    ecpu_freqs: Vec<u32>,
+    pcpu_freqs: Vec<u32>,
+    subscription: Option<IOReportSubscription>,
+    prev: Option<(CVoidRef, Instant)>,
 }
 
-/// Parse power metrics from a delta sample.
-pub fn parse_power(delta: CVoidRef, interval_ns: u64) -> PowerMetrics {
-    unsafe {
-        let items = get_channels_array(delta);
-        if items.is_null() {
-            return PowerMetrics::default();
+// The subscription and samples are plain CF objects that are only touched
+// from the sampler thread after being moved there.
+unsafe impl Send for IOReportSource {}
+
+impl IOReportSource {
+    pub fn new(soc: &SocInfo) -> Self {
+        Self {
+            gpu_freqs: soc.gpu_freqs.clone(),
+            ecpu_freqs: soc.ecpu_freqs.clone(),
+            pcpu_freqs: soc.pcpu_freqs.clone(),
+            subscription: None,
+            prev: None,
         }
+    }
+}
 
-        let count = cfarray_count(items);
-        let mut cpu_joules: f64 = 0.0;
-        let mut gpu_joules: f64 = 0.0;
+impl MetricSource for IOReportSource {
+    fn describe(&self) -> &str {
+        "ioreport (CPU Stats, GPU Stats, Energy Model)"
+    }
 
-        for i in 0..count {
-            let ch = cfarray_get(items, i);
-            if ch.is_null() {
-                continue;
-            }
+    fn init(&mut self) -> io::Result<()> {
+        // Subscribe to IOReport channels for CPU/GPU stats and energy
+        let sub = create_subscription(&["CPU Stats", "GPU Stats", "Energy Model"])
+            .ok_or_else(|| io::Error::other("IOReport subscription failed"))?;
+        self.prev = Some((create_sample(&sub), Instant::now()));
+        self.subscription = Some(sub);
+        Ok(())
+    }
 
-            let group = from_cfstring(IOReportChannelGetGroup(ch));
-            if group.as_deref() != Some("Energy Model") {
-                continue;
-            }
+    fn sample(&mut self, metrics: &mut Metrics) {
+        let (Some(sub), Some((s1, t1))) = (&self.subscription, self.prev) else {
+            return;
+        };
 
-            let channel_name = from_cfstring(IOReportChannelGetChannelName(ch))
-                .unwrap_or_default();
-            let unit = from_cfstring(IOReportChannelGetUnitLabel(ch))
-                .unwrap_or_default();
-            let value = IOReportSimpleGetIntegerValue(ch, 0);
+        let s2 = create_sample(sub);
+        let elapsed_ns = t1.elapsed().as_nanos() as u64;
+        self.prev = Some((s2, Instant::now()));
 
-            if channel_name == "GPU Energy" {
-                gpu_joules += energy_to_joules(value, &unit);
-            } else if channel_name == "CPU Energy" {
-                cpu_joules += energy_to_joules(value, &unit);
-            }
-        }
+        let raw = create_delta(s1, s2);
+        release_sample(s1);
+        let delta = IOReportDelta::from_cf(raw);
+        release_sample(raw);
 
-        let interval_s = interval_ns as f64 / 1_000_000_000.0;
+        let (gpu_util, gpu_freq) = parse_gpu_stats(&delta, &self.gpu_freqs);
+        let power = parse_power(&delta, elapsed_ns);
+        metrics.cpu_clusters = parse_cpu_clusters(&delta, &self.ecpu_freqs, &self.pcpu_freqs);
 
-        let cpu_watts = if interval_s > 0.0 {
-            cpu_joules / interval_s
-        } else {
-            0.0
-        };
-        let gpu_watts = if interval_s > 0.0 {
-            gpu_joules / interval_s
-        } else {
-            0.0
-        };
+        metrics.gpu.utilization = gpu_util;
+        metrics.gpu.freq_mhz = gpu_freq;
+        metrics.gpu.power_watts = power.gpu_watts;
+        metrics.gpu.residency = parse_gpu_residency(&delta);
+        metrics.power = power;
+    }
+}
 
-        PowerMetrics {
-            cpu_watts,
-            gpu_watts,
+impl Drop for IOReportSource {
+    fn drop(&mut self) {
+        if let Some((sample, _)) = self.prev.take() {
+            release_sample(sample);
         }
     }
 }
@@ -0,0 +1,498 @@
+// Off macOS the parsers are only reachable from recorded fixtures.
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+#[cfg(target_os = "macos")]
+mod ffi;
+
+#[cfg(target_os = "macos")]
+pub use ffi::IOReportSource;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Owned copy of one IOReport delta sample.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IOReportDelta {
+    pub channels: Vec<Channel>,
+}
+
+/// One channel of an IOReport sample.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Channel {
+    pub group: String,
+    pub subgroup: String,
+    pub name: String,
+    pub unit: String,
+    /// Integer value of a simple channel (e.g. an energy counter).
+    pub value: i64,
+    /// States of a state channel, in IOReport index order.
+    pub states: Vec<State>,
+}
+
+/// A named state and the time spent in it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    pub name: String,
+    pub residency: i64,
+}
+
+impl IOReportDelta {
+    /// Channels belonging to the given group.
+    pub fn group<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a Channel> + 'a {
+        self.channels.iter().filter(move |ch| ch.group == group)
+    }
+}
+
+/// Parsed GPU metrics from IOReport delta.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuMetrics {
+    pub utilization: f64,
+    pub freq_mhz: f64,
+    pub power_watts: f64,
+    // From the accelerator's PerformanceStatistics rather than IOReport
+    pub device_utilization: f64,
+    pub renderer_utilization: f64,
+    pub tiler_utilization: f64,
+    /// Unified memory the GPU has in use and has allocated.
+    pub in_use_memory_bytes: u64,
+    pub alloc_memory_bytes: u64,
+    /// Time spent in OFF and each P-state, in GPUPH order.
+    pub residency: Vec<PStateResidency>,
+}
+
+/// Share of the interval the GPU spent in one performance state.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PStateResidency {
+    /// "OFF", "P1", "P2", ...
+    pub name: String,
+    pub percent: f64,
+}
+
+/// Active residency and average frequency of one CPU cluster type.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CpuClusterMetrics {
+    /// "E" or "P".
+    pub name: String,
+    pub active_percent: f64,
+    pub freq_mhz: f64,
+}
+
+/// Parsed CPU power from IOReport delta.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerMetrics {
+    pub cpu_watts: f64,
+    pub gpu_watts: f64,
+    /// Whole SoC/CPU package excluding DRAM: reported by RAPL, summed from
+    /// the individual domains on Apple Silicon.
+    pub package_watts: f64,
+    pub dram_watts: f64,
+    /// Apple Neural Engine.
+    pub ane_watts: f64,
+    /// Image signal processor (camera).
+    pub isp_watts: f64,
+    /// Video encode/decode, ProRes and JPEG engines.
+    pub media_watts: f64,
+    /// CPU power per cluster, E first.
+    pub clusters: Vec<ClusterPower>,
+    /// Whole machine, from the SMC's `PSTR` key; 0 where unavailable.
+    pub system_watts: f64,
+}
+
+/// Power of one CPU cluster and, where reported, each of its cores.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClusterPower {
+    /// "E", "P", or "P0"/"P1" on chips with several P-clusters.
+    pub name: String,
+    pub watts: f64,
+    pub cores: Vec<f64>,
+}
+
+impl PowerMetrics {
+    /// Package power if known, otherwise the sum of the parts.
+    pub fn total_watts(&self) -> f64 {
+        if self.package_watts > 0.0 {
+            self.package_watts + self.dram_watts
+        } else {
+            self.cpu_watts + self.gpu_watts + self.dram_watts
+        }
+    }
+}
+
+/// Parse GPU utilization and frequency from a delta sample.
+/// Uses GPUPH channel from "GPU Stats"/"GPU Performance States".
+/// States are: OFF, P1, P2, ... P15 where P-states map to frequency levels.
+/// gpu_freqs should be sorted ascending (lowest freq first) matching P1, P2, ...
+pub fn parse_gpu_stats(delta: &IOReportDelta, gpu_freqs: &[u32]) -> (f64, f64) {
+    let mut total_active: i64 = 0;
+    let mut total: i64 = 0;
+    let mut weighted_freq: f64 = 0.0;
+
+    for ch in delta.group("GPU Stats") {
+        if ch.subgroup != "GPU Performance States" || ch.name != "GPUPH" {
+            continue;
+        }
+
+        for state in &ch.states {
+            total += state.residency;
+
+            // OFF state = GPU is completely off
+            if state.name == "OFF" {
+                continue;
+            }
+
+            // Active P-states: P1, P2, ..., P15
+            // P-state index maps to gpu_freqs array (P1 -> freqs[0], P2 -> freqs[1], etc.)
+            total_active += state.residency;
+
+            if let Some(idx) = state.name.strip_prefix('P').and_then(|n| n.parse::<usize>().ok()) {
+                if idx > 0 && idx <= gpu_freqs.len() {
+                    weighted_freq += gpu_freqs[idx - 1] as f64 * state.residency as f64;
+                }
+            }
+        }
+    }
+
+    let utilization = if total > 0 {
+        (total_active as f64 / total as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    let avg_freq = if total_active > 0 {
+        weighted_freq / total_active as f64
+    } else {
+        0.0
+    };
+
+    (utilization, avg_freq)
+}
+
+/// Per-state GPU residency from the same GPUPH channel as `parse_gpu_stats`,
+/// as a percentage of the interval. Empty if the channel is missing.
+pub fn parse_gpu_residency(delta: &IOReportDelta) -> Vec<PStateResidency> {
+    let Some(ch) = delta
+        .group("GPU Stats")
+        .find(|ch| ch.subgroup == "GPU Performance States" && ch.name == "GPUPH")
+    else {
+        return Vec::new();
+    };
+
+    let total: i64 = ch.states.iter().map(|s| s.residency).sum();
+    ch.states
+        .iter()
+        .map(|state| PStateResidency {
+            name: state.name.clone(),
+            percent: if total > 0 {
+                state.residency as f64 / total as f64 * 100.0
+            } else {
+                0.0
+            },
+        })
+        .collect()
+}
+
+/// Parse per-cluster CPU activity from "CPU Stats"/"CPU Core Performance States".
+/// Each core has its own channel (ECPU0, PCPU3, ...) whose states are idle
+/// states (IDLE/DOWN/OFF) followed by one state per DVFS level, in the same
+/// order as the cluster's frequency table. Cores of the same type are summed.
+pub fn parse_cpu_clusters(
+    delta: &IOReportDelta,
+    ecpu_freqs: &[u32],
+    pcpu_freqs: &[u32],
+) -> Vec<CpuClusterMetrics> {
+    let mut clusters = Vec::new();
+
+    for (name, prefix, freqs) in [("E", "ECPU", ecpu_freqs), ("P", "PCPU", pcpu_freqs)] {
+        let mut total: i64 = 0;
+        let mut total_active: i64 = 0;
+        let mut weighted_freq: f64 = 0.0;
+        let mut found = false;
+
+        for ch in delta.group("CPU Stats") {
+            if ch.subgroup != "CPU Core Performance States" || !ch.name.starts_with(prefix) {
+                continue;
+            }
+            found = true;
+
+            let mut level = 0;
+            for state in &ch.states {
+                total += state.residency;
+                if matches!(state.name.as_str(), "IDLE" | "DOWN" | "OFF") {
+                    continue;
+                }
+                total_active += state.residency;
+                if let Some(&freq) = freqs.get(level) {
+                    weighted_freq += freq as f64 * state.residency as f64;
+                }
+                level += 1;
+            }
+        }
+
+        if !found {
+            continue;
+        }
+
+        clusters.push(CpuClusterMetrics {
+            name: name.to_string(),
+            active_percent: if total > 0 {
+                total_active as f64 / total as f64 * 100.0
+            } else {
+                0.0
+            },
+            freq_mhz: if total_active > 0 {
+                weighted_freq / total_active as f64
+            } else {
+                0.0
+            },
+        });
+    }
+
+    clusters
+}
+
+/// Convert energy value to joules based on unit string.
+fn energy_to_joules(value: i64, unit: &str) -> f64 {
+    match unit {
+        "nJ" => value as f64 / 1_000_000_000.0,
+        "uJ" => value as f64 / 1_000_000.0,
+        "mJ" => value as f64 / 1_000.0,
+        _ => value as f64 / 1_000_000_000.0, // default to nJ
+    }
+}
+
+/// Energy Model channel prefixes of the media engines: video encoder and
+/// decoder, ProRes and JPEG.
+const MEDIA_PREFIXES: &[&str] = &["AVE", "AVD", "PRORES", "MSR", "JPG"];
+
+/// Where a CPU energy channel belongs: its cluster and, for per-core
+/// channels, the core index within that cluster.
+/// M1 names clusters "EACC_CPU"/"PACC0_CPU" with cores "EACC_CPU0", ...;
+/// later chips use "ECPU"/"PCPU" with cores "ECPU0", "PCPU3", ...
+fn classify_cpu_energy(name: &str) -> Option<(String, Option<usize>)> {
+    let (kind, number, core) = if let Some((prefix, core)) = name.split_once("_CPU") {
+        // "EACC" -> "E", "PACC1" -> "P1"
+        let kind = prefix.chars().next()?;
+        let number = prefix[kind.len_utf8()..].strip_prefix("ACC")?;
+        (kind, number, core)
+    } else {
+        let rest = name.strip_prefix(['E', 'P'])?;
+        let core = rest.strip_prefix("CPU")?;
+        (name.chars().next()?, "", core)
+    };
+
+    if !matches!(kind, 'E' | 'P') || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let cluster = format!("{}{}", kind, number);
+    if core.is_empty() {
+        Some((cluster, None))
+    } else {
+        Some((cluster, Some(core.parse().ok()?)))
+    }
+}
+
+/// Parse power metrics from a delta sample.
+pub fn parse_power(delta: &IOReportDelta, interval_ns: u64) -> PowerMetrics {
+    let mut cpu_joules: f64 = 0.0;
+    let mut gpu_joules: f64 = 0.0;
+    let mut ane_joules: f64 = 0.0;
+    let mut dram_joules: f64 = 0.0;
+    let mut isp_joules: f64 = 0.0;
+    let mut media_joules: f64 = 0.0;
+    // cluster -> (cluster channel joules, per-core joules)
+    let mut clusters: BTreeMap<String, (Option<f64>, Vec<f64>)> = BTreeMap::new();
+
+    for ch in delta.group("Energy Model") {
+        let joules = energy_to_joules(ch.value, &ch.unit);
+        if ch.name == "GPU Energy" {
+            gpu_joules += joules;
+        } else if ch.name == "CPU Energy" {
+            cpu_joules += joules;
+        } else if ch.name.starts_with("ANE") {
+            ane_joules += joules;
+        } else if ch.name.starts_with("DRAM") {
+            dram_joules += joules;
+        } else if ch.name.starts_with("ISP") {
+            isp_joules += joules;
+        } else if MEDIA_PREFIXES.iter().any(|p| ch.name.starts_with(p)) {
+            media_joules += joules;
+        } else if let Some((cluster, core)) = classify_cpu_energy(&ch.name) {
+            let entry = clusters.entry(cluster).or_default();
+            match core {
+                None => *entry.0.get_or_insert(0.0) += joules,
+                Some(idx) => {
+                    if entry.1.len() <= idx {
+                        entry.1.resize(idx + 1, 0.0);
+                    }
+                    entry.1[idx] += joules;
+                }
+            }
+        }
+    }
+
+    let interval_s = interval_ns as f64 / 1_000_000_000.0;
+    let to_watts = |joules: f64| if interval_s > 0.0 { joules / interval_s } else { 0.0 };
+
+    let clusters = clusters
+        .into_iter()
+        .map(|(name, (cluster_joules, core_joules))| ClusterPower {
+            name,
+            // Not every chip reports a cluster total; the cores add up to it
+            watts: to_watts(cluster_joules.unwrap_or_else(|| core_joules.iter().sum())),
+            cores: core_joules.into_iter().map(to_watts).collect(),
+        })
+        .collect();
+
+    let package_joules = cpu_joules + gpu_joules + ane_joules + isp_joules + media_joules;
+
+    PowerMetrics {
+        cpu_watts: to_watts(cpu_joules),
+        gpu_watts: to_watts(gpu_joules),
+        package_watts: to_watts(package_joules),
+        dram_watts: to_watts(dram_joules),
+        ane_watts: to_watts(ane_joules),
+        isp_watts: to_watts(isp_joules),
+        media_watts: to_watts(media_joules),
+        clusters,
+        ..PowerMetrics::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(json: &str) -> IOReportDelta {
+        serde_json::from_str(json).expect("fixture parses")
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn gpu_utilization_and_frequency_from_gpuph() {
+        let delta = fixture(include_str!("fixtures/gpu_stats.json"));
+        let (utilization, freq) = parse_gpu_stats(&delta, &[400, 800, 1200]);
+        assert_close(utilization, 50.0);
+        // Half the active time at P1, half at P3
+        assert_close(freq, 800.0);
+    }
+
+    #[test]
+    fn gpu_frequency_ignores_states_past_the_table() {
+        let delta = fixture(include_str!("fixtures/gpu_stats.json"));
+        let (utilization, freq) = parse_gpu_stats(&delta, &[400]);
+        assert_close(utilization, 50.0);
+        assert_close(freq, 200.0);
+    }
+
+    #[test]
+    fn gpu_residency_per_state() {
+        let delta = fixture(include_str!("fixtures/gpu_stats.json"));
+        let residency = parse_gpu_residency(&delta);
+        let states: Vec<(&str, f64)> = residency.iter().map(|r| (r.name.as_str(), r.percent)).collect();
+        assert_eq!(states, [("OFF", 50.0), ("P1", 25.0), ("P2", 0.0), ("P3", 25.0)]);
+    }
+
+    #[test]
+    fn missing_gpuph_reads_as_idle() {
+        let (utilization, freq) = parse_gpu_stats(&IOReportDelta::default(), &[400]);
+        assert_eq!((utilization, freq), (0.0, 0.0));
+        assert!(parse_gpu_residency(&IOReportDelta::default()).is_empty());
+    }
+
+    #[test]
+    fn cpu_clusters_from_core_performance_states() {
+        let delta = fixture(include_str!("fixtures/cpu_stats.json"));
+        let clusters = parse_cpu_clusters(&delta, &[600, 1200], &[1000, 2000, 3000]);
+
+        let names: Vec<&str> = clusters.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["E", "P"]);
+
+        // IDLE, DOWN and OFF are idle; the cluster-level channel is ignored
+        assert_close(clusters[0].active_percent, 30.0);
+        assert_close(clusters[1].active_percent, 25.0);
+        // (600 * (100 + 200) + 1200 * 300) / 600 and (2000 * 250 + 3000 * 250) / 500
+        assert_close(clusters[0].freq_mhz, 900.0);
+        assert_close(clusters[1].freq_mhz, 2500.0);
+    }
+
+    #[test]
+    fn cpu_cluster_frequency_without_a_table() {
+        let delta = fixture(include_str!("fixtures/cpu_stats.json"));
+        let clusters = parse_cpu_clusters(&delta, &[], &[1000]);
+
+        assert_close(clusters[0].active_percent, 30.0);
+        assert_eq!(clusters[0].freq_mhz, 0.0);
+        // Levels past the table count as active but add no frequency
+        assert_close(clusters[1].freq_mhz, 0.0);
+        assert_close(clusters[1].active_percent, 25.0);
+    }
+
+    #[test]
+    fn cpu_clusters_need_core_channels() {
+        let delta = fixture(include_str!("fixtures/gpu_stats.json"));
+        let clusters = parse_cpu_clusters(&delta, &[600], &[1000]);
+        // The GPU fixture only has an idle ECPU0
+        assert_eq!(clusters.len(), 1);
+        assert_eq!((clusters[0].active_percent, clusters[0].freq_mhz), (0.0, 0.0));
+        assert!(parse_cpu_clusters(&IOReportDelta::default(), &[600], &[1000]).is_empty());
+    }
+
+    #[test]
+    fn power_from_energy_model_in_mixed_units() {
+        let delta = fixture(include_str!("fixtures/energy_model.json"));
+        // Half a second
+        let power = parse_power(&delta, 500_000_000);
+
+        assert_close(power.cpu_watts, 3.0);
+        assert_close(power.gpu_watts, 1.0);
+        assert_close(power.ane_watts, 0.5);
+        assert_close(power.dram_watts, 0.6);
+        assert_close(power.isp_watts, 0.1);
+        assert_close(power.media_watts, 0.1);
+        assert_close(power.package_watts, 3.0 + 1.0 + 0.5 + 0.1 + 0.1);
+        assert_close(power.total_watts(), power.package_watts + 0.6);
+    }
+
+    #[test]
+    fn cluster_power_from_cluster_and_core_channels() {
+        let delta = fixture(include_str!("fixtures/energy_model.json"));
+        let power = parse_power(&delta, 500_000_000);
+
+        let names: Vec<&str> = power.clusters.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["E", "P0", "P1"]);
+
+        // E has a cluster channel, P0/P1 are summed from their cores
+        assert_close(power.clusters[0].watts, 0.6);
+        assert_eq!(power.clusters[0].cores, [0.2, 0.4]);
+        assert_close(power.clusters[1].watts, 2.0);
+        assert_eq!(power.clusters[1].cores, [0.8, 1.2]);
+        assert_close(power.clusters[2].watts, 0.4);
+    }
+
+    #[test]
+    fn zero_interval_gives_zero_watts() {
+        let delta = fixture(include_str!("fixtures/energy_model.json"));
+        let power = parse_power(&delta, 0);
+        assert_eq!(power.cpu_watts, 0.0);
+        assert_eq!(power.total_watts(), 0.0);
+    }
+
+    #[test]
+    fn cpu_energy_channel_names() {
+        assert_eq!(classify_cpu_energy("EACC_CPU"), Some(("E".to_string(), None)));
+        assert_eq!(classify_cpu_energy("PACC1_CPU3"), Some(("P1".to_string(), Some(3))));
+        assert_eq!(classify_cpu_energy("PCPU2"), Some(("P".to_string(), Some(2))));
+        assert_eq!(classify_cpu_energy("ECPU"), Some(("E".to_string(), None)));
+        assert_eq!(classify_cpu_energy("GPU Energy"), None);
+        assert_eq!(classify_cpu_energy("DCS_CPU"), None);
+    }
+}
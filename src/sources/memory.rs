@@ -1,15 +1,70 @@
 #![allow(deprecated)]
 
+#[cfg(target_os = "macos")]
 use libc::{
     c_int, host_statistics64, mach_host_self, mach_msg_type_number_t,
     vm_statistics64, HOST_VM_INFO64, HOST_VM_INFO64_COUNT,
 };
+use serde::{Deserialize, Serialize};
+use std::io;
+#[cfg(target_os = "macos")]
 use std::mem;
+use std::time::Instant;
 
-#[derive(Debug, Clone, Default)]
+use super::MetricSource;
+use crate::metrics::Metrics;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MemoryInfo {
     pub total_bytes: u64,
     pub used_bytes: u64,
+    // Breakdown in Activity Monitor's categories. App + wired + compressed
+    // make up `used_bytes` on macOS.
+    pub app_bytes: u64,
+    pub wired_bytes: u64,
+    pub compressed_bytes: u64,
+    /// File-backed pages that can be dropped at any time.
+    pub cached_bytes: u64,
+    pub purgeable_bytes: u64,
+    pub free_bytes: u64,
+    pub swap_total_bytes: u64,
+    pub swap_used_bytes: u64,
+    pub pressure: MemoryPressure,
+    // Paging activity per second since the previous sample
+    pub pageins_per_sec: f64,
+    pub pageouts_per_sec: f64,
+    pub compressions_per_sec: f64,
+    pub decompressions_per_sec: f64,
+}
+
+/// The kernel's memory pressure level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryPressure {
+    #[default]
+    Normal,
+    Warn,
+    Critical,
+}
+
+impl MemoryPressure {
+    pub fn label(self) -> &'static str {
+        match self {
+            MemoryPressure::Normal => "normal",
+            MemoryPressure::Warn => "warn",
+            MemoryPressure::Critical => "critical",
+        }
+    }
+}
+
+/// Cumulative paging counters, turned into rates between samples.
+#[derive(Debug, Clone, Copy, Default)]
+struct VmCounters {
+    pageins: u64,
+    pageouts: u64,
+    compressions: u64,
+    decompressions: u64,
 }
 
 impl MemoryInfo {
@@ -27,9 +82,71 @@ impl MemoryInfo {
         }
         self.used_bytes as f64 / self.total_bytes as f64 * 100.0
     }
+
+    pub fn swap_used_gb(&self) -> f64 {
+        self.swap_used_bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+    }
+
+    pub fn swap_total_gb(&self) -> f64 {
+        self.swap_total_bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+    }
+}
+
+/// Memory source backed by `host_statistics64` (macOS) or /proc/meminfo
+/// (Linux). Keeps the previous paging counters to report rates.
+pub struct MemorySource {
+    prev: Option<(VmCounters, Instant)>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self { prev: None }
+    }
+}
+
+impl MetricSource for MemorySource {
+    fn describe(&self) -> &str {
+        if cfg!(target_os = "linux") {
+            "memory (/proc/meminfo)"
+        } else {
+            "memory (host_statistics64)"
+        }
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        let (_, counters) = read_memory();
+        self.prev = Some((counters, Instant::now()));
+        Ok(())
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        let (mut info, counters) = read_memory();
+        let now = Instant::now();
+
+        if let Some((prev, prev_time)) = self.prev {
+            let secs = now.duration_since(prev_time).as_secs_f64();
+            if secs > 0.0 {
+                let rate = |prev: u64, curr: u64| curr.saturating_sub(prev) as f64 / secs;
+                info.pageins_per_sec = rate(prev.pageins, counters.pageins);
+                info.pageouts_per_sec = rate(prev.pageouts, counters.pageouts);
+                info.compressions_per_sec = rate(prev.compressions, counters.compressions);
+                info.decompressions_per_sec = rate(prev.decompressions, counters.decompressions);
+            }
+        }
+        self.prev = Some((counters, now));
+
+        metrics.memory = info;
+    }
+}
+
+/// Current memory usage, without paging rates.
+#[cfg(target_os = "linux")]
+pub fn get_memory_info() -> MemoryInfo {
+    read_memory().0
 }
 
 /// Get total physical memory via sysctl hw.memsize.
+#[cfg(target_os = "macos")]
 fn total_memory() -> u64 {
     let mut size: u64 = 0;
     let mut len = mem::size_of::<u64>();
@@ -46,9 +163,56 @@ fn total_memory() -> u64 {
     size
 }
 
+/// Swap total and used via sysctl vm.swapusage.
+#[cfg(target_os = "macos")]
+fn swap_usage() -> (u64, u64) {
+    let mut usage: libc::xsw_usage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::xsw_usage>();
+    let name = c"vm.swapusage";
+    let ret = unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            &mut usage as *mut libc::xsw_usage as *mut _,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if ret != 0 {
+        return (0, 0);
+    }
+    (usage.xsu_total, usage.xsu_used)
+}
+
+/// Pressure level via sysctl kern.memorystatus_vm_pressure_level, which
+/// reports the kernel's DISPATCH_MEMORYPRESSURE_* value (1, 2 or 4).
+#[cfg(target_os = "macos")]
+fn pressure_level() -> MemoryPressure {
+    let mut level: c_int = 0;
+    let mut len = mem::size_of::<c_int>();
+    let name = c"kern.memorystatus_vm_pressure_level";
+    unsafe {
+        libc::sysctlbyname(
+            name.as_ptr(),
+            &mut level as *mut c_int as *mut _,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        );
+    }
+    match level {
+        4 => MemoryPressure::Critical,
+        2 => MemoryPressure::Warn,
+        _ => MemoryPressure::Normal,
+    }
+}
+
 /// Get current memory usage via host_statistics64.
-pub fn get_memory_info() -> MemoryInfo {
+#[cfg(target_os = "macos")]
+fn read_memory() -> (MemoryInfo, VmCounters) {
     let total = total_memory();
+    let (swap_total, swap_used) = swap_usage();
+    let pressure = pressure_level();
 
     let mut vm_stat: vm_statistics64 = unsafe { mem::zeroed() };
     let mut count: mach_msg_type_number_t = HOST_VM_INFO64_COUNT as _;
@@ -63,10 +227,14 @@ pub fn get_memory_info() -> MemoryInfo {
     };
 
     if kr != 0 {
-        return MemoryInfo {
+        let info = MemoryInfo {
             total_bytes: total,
-            used_bytes: 0,
+            swap_total_bytes: swap_total,
+            swap_used_bytes: swap_used,
+            pressure,
+            ..MemoryInfo::default()
         };
+        return (info, VmCounters::default());
     }
 
     let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) as u64 };
@@ -77,13 +245,224 @@ pub fn get_memory_info() -> MemoryInfo {
     let purgeable = vm_stat.purgeable_count as u64;
     let wired = vm_stat.wire_count as u64;
     let compressor = vm_stat.compressor_page_count as u64;
+    let external = vm_stat.external_page_count as u64;
+    let free = vm_stat.free_count as u64;
 
     // "App Memory" + wired + compressor is what Activity Monitor reports as "Memory Used"
-    let app_memory = internal - purgeable;
+    let app_memory = internal.saturating_sub(purgeable);
     let used = (app_memory + wired + compressor) * page_size;
 
-    MemoryInfo {
+    let info = MemoryInfo {
         total_bytes: total,
         used_bytes: used.min(total),
+        app_bytes: app_memory * page_size,
+        wired_bytes: wired * page_size,
+        compressed_bytes: compressor * page_size,
+        // Activity Monitor's "Cached Files" includes purgeable pages
+        cached_bytes: (external + purgeable) * page_size,
+        purgeable_bytes: purgeable * page_size,
+        free_bytes: free * page_size,
+        swap_total_bytes: swap_total,
+        swap_used_bytes: swap_used,
+        pressure,
+        ..MemoryInfo::default()
+    };
+    let counters = VmCounters {
+        pageins: vm_stat.pageins,
+        pageouts: vm_stat.pageouts,
+        compressions: vm_stat.compressions,
+        decompressions: vm_stat.decompressions,
+    };
+    (info, counters)
+}
+
+/// Get current memory usage from /proc/meminfo.
+/// Used = MemTotal - MemAvailable, which is what `free` reports. The
+/// breakdown maps Linux fields onto Activity Monitor's categories: kernel
+/// memory counts as wired, zswap as compressed, and app is what remains.
+/// Linux has no purgeable memory.
+#[cfg(target_os = "linux")]
+fn read_memory() -> (MemoryInfo, VmCounters) {
+    let Ok(text) = std::fs::read_to_string("/proc/meminfo") else {
+        return (MemoryInfo::default(), VmCounters::default());
+    };
+    let meminfo = parse_meminfo(&text);
+    let field = |key: &str| meminfo.get(key).copied().unwrap_or(0);
+
+    let total = field("MemTotal");
+    let available = field("MemAvailable");
+    let free = field("MemFree");
+    let cached = (field("Buffers") + field("Cached") + field("SReclaimable"))
+        .saturating_sub(field("Shmem"));
+    let wired = field("SUnreclaim") + field("KernelStack") + field("PageTables");
+    let compressed = field("Zswap");
+    let app = total
+        .saturating_sub(free)
+        .saturating_sub(cached)
+        .saturating_sub(wired)
+        .saturating_sub(compressed);
+
+    let info = MemoryInfo {
+        total_bytes: total,
+        used_bytes: total.saturating_sub(available),
+        app_bytes: app,
+        wired_bytes: wired,
+        compressed_bytes: compressed,
+        cached_bytes: cached,
+        purgeable_bytes: 0,
+        free_bytes: free,
+        swap_total_bytes: field("SwapTotal"),
+        swap_used_bytes: field("SwapTotal").saturating_sub(field("SwapFree")),
+        pressure: std::fs::read_to_string("/proc/pressure/memory")
+            .map_or(MemoryPressure::Normal, |text| parse_psi_pressure(&text)),
+        ..MemoryInfo::default()
+    };
+    let counters = std::fs::read_to_string("/proc/vmstat")
+        .map(|text| parse_vmstat(&text))
+        .unwrap_or_default();
+    (info, counters)
+}
+
+/// Paging counters from /proc/vmstat. Swap-ins/outs stand in for macOS
+/// pageins/pageouts and zswap stores/loads for compressions.
+#[cfg(target_os = "linux")]
+fn parse_vmstat(text: &str) -> VmCounters {
+    let mut counters = VmCounters::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        let Ok(value) = value.trim().parse() else {
+            continue;
+        };
+        match key {
+            "pswpin" => counters.pageins = value,
+            "pswpout" => counters.pageouts = value,
+            "zswpout" => counters.compressions = value,
+            "zswpin" => counters.decompressions = value,
+            _ => {}
+        }
+    }
+    counters
+}
+
+/// Map /proc/pressure/memory onto the macOS levels over the last 10
+/// seconds: every task stalled more than 5% of the time is critical, some
+/// task stalled more than 10% of the time is a warning.
+#[cfg(target_os = "linux")]
+fn parse_psi_pressure(text: &str) -> MemoryPressure {
+    let avg10 = |kind: &str| {
+        text.lines()
+            .find(|line| line.starts_with(kind))
+            .and_then(|line| line.split_whitespace().find_map(|f| f.strip_prefix("avg10=")))
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(0.0)
+    };
+    if avg10("full") > 5.0 {
+        MemoryPressure::Critical
+    } else if avg10("some") > 10.0 {
+        MemoryPressure::Warn
+    } else {
+        MemoryPressure::Normal
+    }
+}
+
+/// Parse /proc/meminfo into byte values keyed by field name.
+#[cfg(target_os = "linux")]
+pub fn parse_meminfo(text: &str) -> std::collections::HashMap<&str, u64> {
+    text.lines()
+        .filter_map(|line| {
+            let (key, rest) = line.split_once(':')?;
+            let mut parts = rest.split_whitespace();
+            let value: u64 = parts.next()?.parse().ok()?;
+            let bytes = match parts.next() {
+                Some("kB") => value * 1024,
+                _ => value,
+            };
+            Some((key, bytes))
+        })
+        .collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn psi(some_avg10: &str, full_avg10: &str) -> String {
+        format!(
+            "some avg10={} avg60=1.00 avg300=0.50 total=123456\nfull avg10={} avg60=0.40 avg300=0.10 total=23456\n",
+            some_avg10, full_avg10
+        )
+    }
+
+    #[test]
+    fn psi_thresholds() {
+        assert_eq!(parse_psi_pressure(&psi("0.00", "0.00")), MemoryPressure::Normal);
+        assert_eq!(parse_psi_pressure(&psi("10.00", "5.00")), MemoryPressure::Normal);
+        assert_eq!(parse_psi_pressure(&psi("10.01", "0.00")), MemoryPressure::Warn);
+        assert_eq!(parse_psi_pressure(&psi("3.00", "5.01")), MemoryPressure::Critical);
+        assert_eq!(parse_psi_pressure(&psi("80.00", "40.00")), MemoryPressure::Critical);
+    }
+
+    #[test]
+    fn psi_without_full_line() {
+        // Kernels before 5.13 have no "full" line at the system level
+        let text = "some avg10=25.00 avg60=1.00 avg300=0.50 total=123456\n";
+        assert_eq!(parse_psi_pressure(text), MemoryPressure::Warn);
+        assert_eq!(parse_psi_pressure(""), MemoryPressure::Normal);
+    }
+
+    #[test]
+    fn vmstat_swap_and_zswap_counters() {
+        let text = "\
+nr_free_pages 1234567
+pgpgin 9876543
+pgpgout 8765432
+pswpin 1200
+pswpout 3400
+zswpin 560
+zswpout 780
+zswpwb 12
+";
+        let counters = parse_vmstat(text);
+        assert_eq!((counters.pageins, counters.pageouts), (1200, 3400));
+        assert_eq!((counters.compressions, counters.decompressions), (780, 560));
+    }
+
+    #[test]
+    fn vmstat_without_zswap() {
+        let counters = parse_vmstat("pswpin 5\npswpout 6\nbogus line\n");
+        assert_eq!((counters.pageins, counters.pageouts), (5, 6));
+        assert_eq!((counters.compressions, counters.decompressions), (0, 0));
+    }
+
+    #[test]
+    fn meminfo_fields_in_bytes() {
+        let text = "\
+MemTotal:       16303428 kB
+MemFree:         1234567 kB
+MemAvailable:    9876543 kB
+Buffers:          123456 kB
+Cached:          4567890 kB
+SwapCached:            0 kB
+Zswap:             65536 kB
+HugePages_Total:       0
+Hugepagesize:       2048 kB
+";
+        let meminfo = parse_meminfo(text);
+        assert_eq!(meminfo["MemTotal"], 16303428 * 1024);
+        assert_eq!(meminfo["Zswap"], 64 * 1024 * 1024);
+        assert_eq!(meminfo["SwapCached"], 0);
+        // Counts without a unit stay as they are
+        assert_eq!(meminfo["HugePages_Total"], 0);
+        assert_eq!(meminfo["Hugepagesize"], 2 * 1024 * 1024);
+        assert_eq!(meminfo.len(), 9);
+    }
+
+    #[test]
+    fn malformed_meminfo_lines_are_skipped() {
+        let meminfo = parse_meminfo("MemTotal: lots kB\nno colon\nMemFree: 5 kB\nEmpty:\n");
+        assert_eq!(meminfo.len(), 1);
+        assert_eq!(meminfo["MemFree"], 5 * 1024);
     }
 }
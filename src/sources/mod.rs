@@ -1,9 +1,74 @@
+#[cfg(target_os = "macos")]
+pub mod accelerator;
+pub mod battery;
+#[cfg(target_os = "macos")]
 pub mod cf_utils;
 pub mod cpu;
+pub mod disk;
+#[cfg(target_os = "macos")]
 pub mod iokit;
 pub mod ioreport;
 pub mod memory;
+pub mod network;
+pub mod process;
+#[cfg(target_os = "linux")]
+pub mod rapl;
+pub mod smc;
+pub mod thermal_pressure;
 
-use std::ffi::c_void;
+use crate::metrics::Metrics;
+use crate::soc::SocInfo;
+use std::io;
 
-pub type CVoidRef = *const c_void;
+#[cfg(target_os = "macos")]
+pub type CVoidRef = *const std::ffi::c_void;
+
+/// A backend that contributes part of a `Metrics` sample.
+///
+/// The sampler calls `init` once on its own thread, then `sample` once per
+/// interval. Sources that track deltas keep their previous reading between
+/// calls and only fill in the fields of `Metrics` they own.
+pub trait MetricSource: Send {
+    /// Short name of the backend, shown in diagnostics.
+    fn describe(&self) -> &str;
+
+    /// Take the baseline reading. Sources that fail here are dropped.
+    fn init(&mut self) -> io::Result<()>;
+
+    /// Read the current values into `metrics`.
+    fn sample(&mut self, metrics: &mut Metrics);
+}
+
+/// The sources used on this platform.
+#[cfg(target_os = "macos")]
+pub fn default_sources(soc: &SocInfo) -> Vec<Box<dyn MetricSource>> {
+    vec![
+        Box::new(cpu::CpuTracker::new()),
+        Box::new(memory::MemorySource::new()),
+        // After the CPU tracker, whose busy time scales the energy shares
+        Box::new(process::ProcessSource::new()),
+        Box::new(network::NetworkSource::new()),
+        Box::new(disk::DiskSource::new()),
+        Box::new(ioreport::IOReportSource::new(soc)),
+        Box::new(accelerator::AcceleratorSource),
+        // After IOReport, which replaces `metrics.power` as a whole
+        Box::new(smc::SmcSource::new()),
+        Box::new(thermal_pressure::ThermalPressureSource::new()),
+        Box::new(battery::BatterySource),
+    ]
+}
+
+/// The sources used on this platform.
+#[cfg(target_os = "linux")]
+pub fn default_sources(_soc: &SocInfo) -> Vec<Box<dyn MetricSource>> {
+    vec![
+        Box::new(cpu::CpuTracker::new()),
+        Box::new(memory::MemorySource::new()),
+        // After the CPU tracker, whose busy time scales the energy shares
+        Box::new(process::ProcessSource::new()),
+        Box::new(network::NetworkSource::new()),
+        Box::new(disk::DiskSource::new()),
+        Box::new(rapl::RaplSource::new()),
+        Box::new(battery::BatterySource),
+    ]
+}
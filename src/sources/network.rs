@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::time::Instant;
+
+use super::MetricSource;
+use crate::metrics::Metrics;
+
+/// Traffic on one network interface.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+    pub rx_packets_per_sec: f64,
+    pub tx_packets_per_sec: f64,
+    /// Bytes since boot, or since the counter last wrapped.
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub loopback: bool,
+    /// Up and running.
+    pub up: bool,
+}
+
+impl NetworkInterface {
+    /// Down, or up but never used (most of macOS's virtual interfaces).
+    pub fn inactive(&self) -> bool {
+        !self.up || self.rx_bytes + self.tx_bytes == 0
+    }
+}
+
+/// Cumulative counters, turned into rates between samples.
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+    rx_bytes: u64,
+    tx_bytes: u64,
+    rx_packets: u64,
+    tx_packets: u64,
+}
+
+/// One interface as read from the system.
+struct InterfaceSample {
+    name: String,
+    counters: Counters,
+    loopback: bool,
+    up: bool,
+}
+
+/// Network source backed by `getifaddrs` (macOS) or /proc/net/dev (Linux).
+/// Keeps the previous counters per interface to report rates.
+pub struct NetworkSource {
+    prev: HashMap<String, Counters>,
+    prev_time: Option<Instant>,
+}
+
+impl NetworkSource {
+    pub fn new() -> Self {
+        Self {
+            prev: HashMap::new(),
+            prev_time: None,
+        }
+    }
+}
+
+impl MetricSource for NetworkSource {
+    fn describe(&self) -> &str {
+        if cfg!(target_os = "linux") {
+            "network (/proc/net/dev)"
+        } else {
+            "network (getifaddrs)"
+        }
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        let samples = read_interfaces()?;
+        self.prev = samples.into_iter().map(|s| (s.name, s.counters)).collect();
+        self.prev_time = Some(Instant::now());
+        Ok(())
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        let Ok(samples) = read_interfaces() else {
+            return;
+        };
+        let now = Instant::now();
+        let secs = self
+            .prev_time
+            .map_or(0.0, |prev| now.duration_since(prev).as_secs_f64());
+
+        let mut interfaces = Vec::with_capacity(samples.len());
+        for sample in &samples {
+            let mut iface = NetworkInterface {
+                name: sample.name.clone(),
+                rx_bytes: sample.counters.rx_bytes,
+                tx_bytes: sample.counters.tx_bytes,
+                loopback: sample.loopback,
+                up: sample.up,
+                ..NetworkInterface::default()
+            };
+            // Interfaces that just appeared get their rates next time
+            if let (Some(prev), true) = (self.prev.get(&sample.name), secs > 0.0) {
+                let curr = sample.counters;
+                let rate = |prev: u64, curr: u64| {
+                    counter_delta(prev, curr, COUNTERS_WRAP_AT_32_BITS) as f64 / secs
+                };
+                iface.rx_bytes_per_sec = rate(prev.rx_bytes, curr.rx_bytes);
+                iface.tx_bytes_per_sec = rate(prev.tx_bytes, curr.tx_bytes);
+                iface.rx_packets_per_sec = rate(prev.rx_packets, curr.rx_packets);
+                iface.tx_packets_per_sec = rate(prev.tx_packets, curr.tx_packets);
+            }
+            interfaces.push(iface);
+        }
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+        self.prev = samples.into_iter().map(|s| (s.name, s.counters)).collect();
+        self.prev_time = Some(now);
+        metrics.network = interfaces;
+    }
+}
+
+/// Whether a counter that went backwards has wrapped rather than been
+/// reset: `if_data` counters are 32-bit on macOS, while /proc/net/dev
+/// counters are 64-bit and only go backwards when a driver resets them.
+const COUNTERS_WRAP_AT_32_BITS: bool = cfg!(target_os = "macos");
+
+/// Difference between two readings of a counter. A counter that went
+/// backwards has wrapped if `wrap_32` is set and it fits in 32 bits;
+/// anything else was reset and counts as no traffic.
+fn counter_delta(prev: u64, curr: u64, wrap_32: bool) -> u64 {
+    if curr >= prev {
+        curr - prev
+    } else if wrap_32 && prev <= u32::MAX as u64 {
+        curr + (1u64 << 32) - prev
+    } else {
+        0
+    }
+}
+
+/// Walk `getifaddrs` for the link-level entries, which carry the `if_data`
+/// counters.
+#[cfg(target_os = "macos")]
+fn read_interfaces() -> io::Result<Vec<InterfaceSample>> {
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut samples = Vec::new();
+    let mut cursor = addrs;
+    while !cursor.is_null() {
+        let ifa = unsafe { &*cursor };
+        cursor = ifa.ifa_next;
+
+        if ifa.ifa_addr.is_null() || ifa.ifa_data.is_null() {
+            continue;
+        }
+        if unsafe { (*ifa.ifa_addr).sa_family } as i32 != libc::AF_LINK {
+            continue;
+        }
+        let data = unsafe { &*(ifa.ifa_data as *const libc::if_data) };
+        let name = unsafe { std::ffi::CStr::from_ptr(ifa.ifa_name) };
+        let flags = ifa.ifa_flags as i32;
+
+        samples.push(InterfaceSample {
+            name: name.to_string_lossy().into_owned(),
+            counters: Counters {
+                rx_bytes: data.ifi_ibytes as u64,
+                tx_bytes: data.ifi_obytes as u64,
+                rx_packets: data.ifi_ipackets as u64,
+                tx_packets: data.ifi_opackets as u64,
+            },
+            loopback: flags & libc::IFF_LOOPBACK != 0,
+            up: flags & libc::IFF_UP != 0 && flags & libc::IFF_RUNNING != 0,
+        });
+    }
+
+    unsafe { libc::freeifaddrs(addrs) };
+    Ok(samples)
+}
+
+#[cfg(target_os = "linux")]
+fn read_interfaces() -> io::Result<Vec<InterfaceSample>> {
+    let text = std::fs::read_to_string("/proc/net/dev")?;
+    Ok(parse_net_dev(&text)
+        .into_iter()
+        .map(|(name, counters)| {
+            let read = |file: &str| {
+                std::fs::read_to_string(format!("/sys/class/net/{}/{}", name, file))
+                    .unwrap_or_default()
+            };
+            let flags = i32::from_str_radix(read("flags").trim().trim_start_matches("0x"), 16)
+                .unwrap_or(0);
+            let operstate = read("operstate");
+            InterfaceSample {
+                loopback: flags & libc::IFF_LOOPBACK != 0,
+                up: link_up(flags, operstate.trim()),
+                name,
+                counters,
+            }
+        })
+        .collect())
+}
+
+/// Whether a Linux interface is up and running. The sysfs `flags` only hold
+/// the administrative flags (never `IFF_RUNNING`), so the link state comes
+/// from `operstate`, which is "unknown" for drivers that don't report one
+/// (loopback, tun).
+#[cfg(target_os = "linux")]
+fn link_up(flags: i32, operstate: &str) -> bool {
+    flags & libc::IFF_UP != 0 && matches!(operstate, "up" | "unknown")
+}
+
+/// Parse /proc/net/dev: two header lines, then `name: ` followed by eight
+/// receive and eight transmit fields, bytes and packets first in each.
+#[cfg(target_os = "linux")]
+fn parse_net_dev(text: &str) -> Vec<(String, Counters)> {
+    text.lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, fields) = line.split_once(':')?;
+            let fields: Vec<u64> = fields
+                .split_whitespace()
+                .map(|f| f.parse().unwrap_or(0))
+                .collect();
+            if fields.len() < 10 {
+                return None;
+            }
+            Some((
+                name.trim().to_string(),
+                Counters {
+                    rx_bytes: fields[0],
+                    rx_packets: fields[1],
+                    tx_bytes: fields[8],
+                    tx_packets: fields[9],
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_count_up() {
+        assert_eq!(counter_delta(100, 250, false), 150);
+        assert_eq!(counter_delta(100, 250, true), 150);
+        assert_eq!(counter_delta(7, 7, true), 0);
+    }
+
+    #[test]
+    fn macos_counters_wrap_at_32_bits() {
+        assert_eq!(counter_delta(u32::MAX as u64 - 9, 20, true), 30);
+    }
+
+    #[test]
+    fn counters_that_go_backwards_were_reset() {
+        // 64-bit counters (Linux) never wrap in practice
+        assert_eq!(counter_delta(u32::MAX as u64 - 9, 20, false), 0);
+        assert_eq!(counter_delta(1000, 20, false), 0);
+        // Past 32 bits it can't have been a 32-bit wrap
+        assert_eq!(counter_delta(1 << 40, 20, true), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn net_dev_counters() {
+        let text = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 1234567    8901    0    0    0     0          0         0  1234567    8901    0    0    0     0       0          0
+  eth0:98765432  123456    0    3    0     0          0       17 45678901   65432    0    0    0     0       0          0
+ wlan0:       0       0    0    0    0     0          0         0        0       0    0    0    0     0       0          0
+";
+        let interfaces = parse_net_dev(text);
+        let names: Vec<&str> = interfaces.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["lo", "eth0", "wlan0"]);
+
+        let eth0 = interfaces[1].1;
+        assert_eq!((eth0.rx_bytes, eth0.rx_packets), (98765432, 123456));
+        assert_eq!((eth0.tx_bytes, eth0.tx_packets), (45678901, 65432));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn short_net_dev_lines_are_skipped() {
+        let text = "header\nheader\n  eth0: 1 2 3\nno colon here\n";
+        assert!(parse_net_dev(text).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn link_state_from_operstate() {
+        // lo: IFF_UP | IFF_LOOPBACK, with no IFF_RUNNING in sysfs
+        assert!(link_up(0x9, "unknown"));
+        // eth0 with a cable, without one, and administratively down
+        assert!(link_up(0x1003, "up"));
+        assert!(!link_up(0x1003, "down"));
+        assert!(!link_up(0x1002, "down"));
+        // tun devices report "unknown" while up
+        assert!(link_up(0x1091, "unknown"));
+        assert!(!link_up(0x1090, "unknown"));
+        assert!(!link_up(0x1003, "dormant"));
+    }
+}
@@ -0,0 +1,491 @@
+#![allow(deprecated)]
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::io;
+#[cfg(target_os = "macos")]
+use std::mem;
+use std::time::Instant;
+
+#[cfg(target_os = "macos")]
+use super::iokit::{self, GpuClient};
+use super::MetricSource;
+use crate::metrics::Metrics;
+
+/// How many of the top processes to keep per sort key. Keeping every
+/// process would bloat recordings and JSON output for no visible gain.
+const TOP_PROCESSES: usize = 40;
+
+/// `proc_listpids` type for every process (from <sys/proc_info.h>).
+#[cfg(target_os = "macos")]
+const PROC_ALL_PIDS: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    /// CPU time over wall time; can exceed 100% for multithreaded processes.
+    pub cpu_percent: f64,
+    /// GPU time over wall time, where the GPU driver reports it (macOS).
+    pub gpu_percent: f64,
+    pub resident_bytes: u64,
+    pub threads: u32,
+    /// Estimated power: this process's share of the measured CPU power.
+    pub energy_watts: f64,
+    /// Fraction of all CPU work attributed to this process, by billed
+    /// energy where the OS reports it and by CPU time otherwise.
+    #[serde(skip)]
+    pub energy_share: f64,
+}
+
+/// One reading of a process before its counters are turned into rates.
+struct ProcessSample {
+    pid: u32,
+    name: String,
+    counters: Counters,
+    resident_bytes: u64,
+    threads: u32,
+}
+
+/// Cumulative per-process counters, kept between samples.
+#[derive(Debug, Clone, Copy)]
+struct Counters {
+    cpu_ns: u64,
+    /// Energy the kernel billed to the process, in nanojoules.
+    billed_nj: Option<u64>,
+    gpu_ns: u64,
+}
+
+/// Per-process CPU, GPU, memory and threads from libproc and the GPU
+/// driver's user clients (macOS) or /proc/<pid> (Linux). Keeps each
+/// process's counters between samples.
+pub struct ProcessSource {
+    prev: HashMap<u32, Counters>,
+    prev_time: Instant,
+}
+
+impl ProcessSource {
+    pub fn new() -> Self {
+        Self {
+            prev: HashMap::new(),
+            prev_time: Instant::now(),
+        }
+    }
+}
+
+impl MetricSource for ProcessSource {
+    fn describe(&self) -> &str {
+        if cfg!(target_os = "linux") {
+            "processes (/proc/<pid>)"
+        } else {
+            "processes (libproc)"
+        }
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        let samples = read_processes()?;
+        self.prev = samples.iter().map(|p| (p.pid, p.counters)).collect();
+        self.prev_time = Instant::now();
+        Ok(())
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        let Ok(samples) = read_processes() else {
+            return;
+        };
+        let now = Instant::now();
+        let wall_ns = now.duration_since(self.prev_time).as_nanos() as f64;
+        self.prev_time = now;
+
+        let mut processes = Vec::with_capacity(samples.len());
+        let mut deltas = Vec::with_capacity(samples.len());
+        let mut prev = HashMap::with_capacity(samples.len());
+        let percent = |delta: u64| {
+            if wall_ns > 0.0 {
+                delta as f64 / wall_ns * 100.0
+            } else {
+                0.0
+            }
+        };
+        for sample in samples {
+            // Processes started during the interval count from zero
+            let before = self.prev.get(&sample.pid).copied().unwrap_or(Counters {
+                cpu_ns: 0,
+                billed_nj: Some(0),
+                gpu_ns: 0,
+            });
+            let now = sample.counters;
+            let cpu_delta = now.cpu_ns.saturating_sub(before.cpu_ns);
+            let billed_delta = match (now.billed_nj, before.billed_nj) {
+                (Some(now), Some(before)) => now.saturating_sub(before),
+                _ => 0,
+            };
+            let gpu_delta = now.gpu_ns.saturating_sub(before.gpu_ns);
+            prev.insert(sample.pid, now);
+            deltas.push((cpu_delta, billed_delta));
+            processes.push(ProcessInfo {
+                pid: sample.pid,
+                name: sample.name,
+                cpu_percent: percent(cpu_delta),
+                gpu_percent: percent(gpu_delta),
+                resident_bytes: sample.resident_bytes,
+                threads: sample.threads,
+                ..ProcessInfo::default()
+            });
+        }
+        self.prev = prev;
+
+        // All CPU time spent in the interval, including processes we can't
+        // read; the CPU tracker has already sampled
+        let busy_ns = metrics.cpu.overall_percent / 100.0 * metrics.cpu.per_core.len() as f64 * wall_ns;
+        for (process, share) in processes.iter_mut().zip(energy_shares(&deltas, busy_ns)) {
+            process.energy_share = share;
+        }
+
+        metrics.processes = top_processes(processes);
+    }
+}
+
+/// Each process's fraction of all CPU work, from its (CPU time, billed
+/// energy) deltas. Billed energy accounts for core type and frequency, so
+/// it's the better weight; CPU time is the fallback where it isn't reported.
+/// The processes we can read only get the part of `busy_ns` (system-wide
+/// CPU time) they used, so the rest of the power stays with the processes
+/// we can't read. `busy_ns` of zero means unknown.
+fn energy_shares(deltas: &[(u64, u64)], busy_ns: f64) -> Vec<f64> {
+    let total_billed: u64 = deltas.iter().map(|d| d.1).sum();
+    let total_cpu: u64 = deltas.iter().map(|d| d.0).sum();
+    // Tick and task time granularity can put the readable total slightly
+    // above the system's
+    let cpu_total = busy_ns.max(total_cpu as f64);
+    let readable = if cpu_total > 0.0 { total_cpu as f64 / cpu_total } else { 0.0 };
+
+    deltas
+        .iter()
+        .map(|&(cpu_delta, billed_delta)| {
+            if total_billed > 0 {
+                billed_delta as f64 / total_billed as f64 * readable
+            } else if cpu_total > 0.0 {
+                cpu_delta as f64 / cpu_total
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// Split the measured CPU power across processes by their energy share.
+/// Runs after all sources have sampled, since the power comes from another
+/// source.
+pub fn attribute_power(metrics: &mut Metrics) {
+    let cpu_watts = metrics.power.cpu_watts;
+    for process in &mut metrics.processes {
+        process.energy_watts = process.energy_share * cpu_watts;
+    }
+}
+
+/// Keep the top processes by CPU, GPU, energy, memory and thread count,
+/// ordered by CPU.
+fn top_processes(mut processes: Vec<ProcessInfo>) -> Vec<ProcessInfo> {
+    let mut keep = HashSet::new();
+    processes.sort_by(|a, b| b.gpu_percent.total_cmp(&a.gpu_percent));
+    keep.extend(processes.iter().take(TOP_PROCESSES).map(|p| p.pid));
+    processes.sort_by(|a, b| b.energy_share.total_cmp(&a.energy_share));
+    keep.extend(processes.iter().take(TOP_PROCESSES).map(|p| p.pid));
+    processes.sort_by_key(|p| Reverse(p.threads));
+    keep.extend(processes.iter().take(TOP_PROCESSES).map(|p| p.pid));
+    processes.sort_by_key(|p| Reverse(p.resident_bytes));
+    keep.extend(processes.iter().take(TOP_PROCESSES).map(|p| p.pid));
+    processes.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent));
+    keep.extend(processes.iter().take(TOP_PROCESSES).map(|p| p.pid));
+
+    processes.retain(|p| keep.contains(&p.pid));
+    processes
+}
+
+/// Split the `IOUserClientCreator` of a GPU user client, like
+/// "pid 123, WindowServer", into the PID and process name.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn parse_client_creator(creator: &str) -> Option<(u32, &str)> {
+    let (pid, name) = creator.strip_prefix("pid ")?.split_once(',')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some((pid.trim().parse().ok()?, name))
+}
+
+/// Read every process we're allowed to inspect via libproc. Processes of
+/// other users (and root) are skipped without sudo.
+#[cfg(target_os = "macos")]
+fn read_processes() -> io::Result<Vec<ProcessSample>> {
+    // proc_listpids with a null buffer returns the size it needs
+    let needed = unsafe { libc::proc_listpids(PROC_ALL_PIDS, 0, std::ptr::null_mut(), 0) };
+    if needed <= 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Leave room for processes spawned between the two calls
+    let mut pids = vec![0 as libc::c_int; needed as usize / mem::size_of::<libc::c_int>() + 64];
+    let bytes = unsafe {
+        libc::proc_listpids(
+            PROC_ALL_PIDS,
+            0,
+            pids.as_mut_ptr() as *mut _,
+            (pids.len() * mem::size_of::<libc::c_int>()) as libc::c_int,
+        )
+    };
+    if bytes <= 0 {
+        return Err(io::Error::last_os_error());
+    }
+    pids.truncate(bytes as usize / mem::size_of::<libc::c_int>());
+
+    // Task times are in mach absolute time units, not nanoseconds
+    let mut timebase = libc::mach_timebase_info { numer: 0, denom: 0 };
+    unsafe { libc::mach_timebase_info(&mut timebase) };
+    let (numer, denom) = if timebase.denom == 0 {
+        (1, 1)
+    } else {
+        (timebase.numer as u128, timebase.denom as u128)
+    };
+
+    let mut gpu_clients: HashMap<u32, GpuClient> =
+        iokit::gpu_clients().into_iter().map(|c| (c.pid, c)).collect();
+
+    let mut samples = Vec::with_capacity(pids.len());
+    for pid in pids.into_iter().filter(|&pid| pid > 0) {
+        let mut info: libc::proc_taskinfo = unsafe { mem::zeroed() };
+        let size = mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+        let ret = unsafe {
+            libc::proc_pidinfo(
+                pid,
+                libc::PROC_PIDTASKINFO,
+                0,
+                &mut info as *mut libc::proc_taskinfo as *mut _,
+                size,
+            )
+        };
+        if ret != size {
+            continue;
+        }
+
+        let ticks = info.pti_total_user + info.pti_total_system;
+        samples.push(ProcessSample {
+            pid: pid as u32,
+            name: process_name(pid),
+            counters: Counters {
+                cpu_ns: (ticks as u128 * numer / denom) as u64,
+                billed_nj: billed_energy(pid),
+                gpu_ns: gpu_clients.remove(&(pid as u32)).map_or(0, |c| c.gpu_time_ns),
+            },
+            resident_bytes: info.pti_resident_size,
+            threads: info.pti_threadnum.max(0) as u32,
+        });
+    }
+
+    // GPU clients we can't inspect (e.g. WindowServer without sudo) still
+    // show up with their GPU time
+    samples.extend(gpu_clients.into_values().map(|client| ProcessSample {
+        pid: client.pid,
+        name: client.name,
+        counters: Counters {
+            cpu_ns: 0,
+            billed_nj: None,
+            gpu_ns: client.gpu_time_ns,
+        },
+        resident_bytes: 0,
+        threads: 0,
+    }));
+    Ok(samples)
+}
+
+/// Energy billed to the process (including work done on its behalf by
+/// other processes), via proc_pid_rusage.
+#[cfg(target_os = "macos")]
+fn billed_energy(pid: libc::c_int) -> Option<u64> {
+    let mut usage: libc::rusage_info_v4 = unsafe { mem::zeroed() };
+    let ret = unsafe {
+        libc::proc_pid_rusage(
+            pid,
+            libc::RUSAGE_INFO_V4,
+            &mut usage as *mut libc::rusage_info_v4 as *mut libc::rusage_info_t,
+        )
+    };
+    (ret == 0).then_some(usage.ri_billed_energy)
+}
+
+#[cfg(target_os = "macos")]
+fn process_name(pid: libc::c_int) -> String {
+    let mut buf = [0u8; 2 * libc::MAXCOMLEN + 1];
+    let len = unsafe { libc::proc_name(pid, buf.as_mut_ptr() as *mut _, buf.len() as u32) };
+    if len <= 0 {
+        return format!("<{}>", pid);
+    }
+    String::from_utf8_lossy(&buf[..len as usize]).into_owned()
+}
+
+/// Read every process from /proc.
+#[cfg(target_os = "linux")]
+fn read_processes() -> io::Result<Vec<ProcessSample>> {
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+
+    let mut samples = Vec::new();
+    for entry in std::fs::read_dir("/proc")? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else {
+            continue;
+        };
+        // The process may exit between listing and reading
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        let Some(stat) = parse_pid_stat(&stat) else {
+            continue;
+        };
+        samples.push(ProcessSample {
+            pid,
+            name: stat.name,
+            counters: Counters {
+                cpu_ns: stat.cpu_ticks * 1_000_000_000 / ticks_per_sec,
+                billed_nj: None,
+                gpu_ns: 0,
+            },
+            resident_bytes: stat.rss_pages * page_size,
+            threads: stat.threads,
+        });
+    }
+    Ok(samples)
+}
+
+#[cfg(target_os = "linux")]
+struct PidStat {
+    name: String,
+    cpu_ticks: u64,
+    threads: u32,
+    rss_pages: u64,
+}
+
+/// Parse /proc/<pid>/stat. The command name is in parentheses and may itself
+/// contain spaces and parentheses, so the fields are split after the last ')'.
+#[cfg(target_os = "linux")]
+fn parse_pid_stat(text: &str) -> Option<PidStat> {
+    let open = text.find('(')?;
+    let close = text.rfind(')')?;
+    let name = text.get(open + 1..close)?.to_string();
+
+    // Fields after the name, starting with field 3 (state)
+    let fields: Vec<&str> = text[close + 1..].split_whitespace().collect();
+    let field = |n: usize| -> Option<u64> { fields.get(n - 3)?.parse().ok() };
+
+    Some(PidStat {
+        name,
+        cpu_ticks: field(14)? + field(15)?,
+        threads: field(20)? as u32,
+        rss_pages: field(24)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A process that ranks by its PID on every key but memory.
+    fn process(pid: u32, resident_bytes: u64) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: format!("p{}", pid),
+            cpu_percent: pid as f64,
+            gpu_percent: pid as f64,
+            resident_bytes,
+            threads: pid,
+            energy_share: pid as f64,
+            ..ProcessInfo::default()
+        }
+    }
+
+    #[test]
+    fn top_processes_keep_the_top_of_every_key_ordered_by_cpu() {
+        let mut processes: Vec<ProcessInfo> =
+            (1..=100).map(|pid| process(pid, 0)).collect();
+        // Idle, but the largest by memory
+        processes.push(ProcessInfo { resident_bytes: 1 << 30, ..process(0, 0) });
+
+        let top = top_processes(processes);
+        assert_eq!(top.len(), TOP_PROCESSES + 1);
+        assert!(top.iter().any(|p| p.pid == 0));
+        assert_eq!(top[0].pid, 100);
+        assert!(top.windows(2).all(|w| w[0].cpu_percent >= w[1].cpu_percent));
+        assert!(!top.iter().any(|p| p.pid == 1));
+    }
+
+    #[test]
+    fn energy_shares_leave_room_for_unreadable_processes() {
+        // Readable processes used 2 of the 4 busy seconds
+        let shares = energy_shares(&[(1_500_000_000, 0), (500_000_000, 0)], 4e9);
+        assert_eq!(shares, [0.375, 0.125]);
+
+        // Billed energy splits the readable part
+        let shares = energy_shares(&[(1_000_000_000, 100), (1_000_000_000, 300)], 4e9);
+        assert_eq!(shares, [0.125, 0.375]);
+    }
+
+    #[test]
+    fn energy_shares_without_system_busy_time() {
+        // Everything was readable, or the CPU tracker is unavailable
+        let shares = energy_shares(&[(3, 0), (1, 0)], 0.0);
+        assert_eq!(shares, [0.75, 0.25]);
+        let shares = energy_shares(&[(3, 0), (1, 0)], 2.0);
+        assert_eq!(shares, [0.75, 0.25]);
+        assert_eq!(energy_shares(&[(0, 0)], 0.0), [0.0]);
+        assert!(energy_shares(&[], 1e9).is_empty());
+    }
+
+    #[test]
+    fn power_is_split_by_energy_share() {
+        let mut metrics = Metrics::default();
+        metrics.power.cpu_watts = 4.0;
+        metrics.processes = vec![
+            ProcessInfo { energy_share: 0.75, ..ProcessInfo::default() },
+            ProcessInfo { energy_share: 0.25, ..ProcessInfo::default() },
+        ];
+        attribute_power(&mut metrics);
+        assert_eq!(metrics.processes[0].energy_watts, 3.0);
+        assert_eq!(metrics.processes[1].energy_watts, 1.0);
+    }
+
+    #[test]
+    fn gpu_client_creator() {
+        assert_eq!(parse_client_creator("pid 123, WindowServer"), Some((123, "WindowServer")));
+        assert_eq!(parse_client_creator("pid 7,Google Chrome Helper (GPU)"), Some((7, "Google Chrome Helper (GPU)")));
+        assert_eq!(parse_client_creator("pid abc, WindowServer"), None);
+        assert_eq!(parse_client_creator("pid -1, WindowServer"), None);
+        assert_eq!(parse_client_creator("123, WindowServer"), None);
+        assert_eq!(parse_client_creator("pid 123"), None);
+        assert_eq!(parse_client_creator("pid 123, "), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pid_stat_with_a_tricky_command_name() {
+        // Fields 14/15 are utime/stime, 20 num_threads, 24 rss
+        let text = "1234 (a b) c) S 1 1234 1234 0 -1 4194560 500 0 0 0 \
+                    150 50 0 0 20 0 7 0 1000 123456789 321 18446744073709551615 \
+                    1 1 0 0 0 0 0 0 0 0 0 0 17 3 0 0 0 0 0\n";
+        let stat = parse_pid_stat(text).unwrap();
+        assert_eq!(stat.name, "a b) c");
+        assert_eq!(stat.cpu_ticks, 200);
+        assert_eq!(stat.threads, 7);
+        assert_eq!(stat.rss_pages, 321);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn truncated_pid_stat_is_rejected() {
+        assert!(parse_pid_stat("1234 (sh) S 1 1234").is_none());
+        assert!(parse_pid_stat("1234 sh S").is_none());
+    }
+}
@@ -0,0 +1,281 @@
+use super::MetricSource;
+use crate::metrics::Metrics;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const DEFAULT_ROOT: &str = "/sys/class/powercap";
+
+/// What a powercap zone measures, from its `name` file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ZoneKind {
+    Package,
+    Core,
+    Uncore,
+    Dram,
+}
+
+impl ZoneKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            n if n.starts_with("package") => Some(ZoneKind::Package),
+            "core" => Some(ZoneKind::Core),
+            "uncore" => Some(ZoneKind::Uncore),
+            "dram" => Some(ZoneKind::Dram),
+            // psys and unknown zones overlap the others
+            _ => None,
+        }
+    }
+}
+
+struct Zone {
+    kind: ZoneKind,
+    energy_path: PathBuf,
+    max_energy_uj: u64,
+    prev_uj: u64,
+}
+
+/// CPU package/core/DRAM power from the RAPL powercap interface.
+///
+/// Reads `intel-rapl*/energy_uj` counters under a sysfs root (normally
+/// `/sys/class/powercap`) and reports the energy delta since the last call.
+pub struct RaplSource {
+    root: PathBuf,
+    zones: Vec<Zone>,
+    prev_time: Instant,
+}
+
+impl RaplSource {
+    pub fn new() -> Self {
+        Self::with_root(DEFAULT_ROOT)
+    }
+
+    /// Read zones from an alternate sysfs root instead of `/sys/class/powercap`.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            zones: Vec::new(),
+            prev_time: Instant::now(),
+        }
+    }
+}
+
+impl MetricSource for RaplSource {
+    fn describe(&self) -> &str {
+        "power (RAPL powercap)"
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("intel-rapl:"))
+            .map(|entry| entry.path())
+            .collect();
+        dirs.sort();
+
+        self.zones.clear();
+        for dir in dirs {
+            let kind = read_string(&dir.join("name")).and_then(|n| ZoneKind::from_name(&n));
+            let Some(kind) = kind else {
+                continue;
+            };
+            let energy_path = dir.join("energy_uj");
+            // energy_uj is root-only on many kernels; surface that here
+            let prev_uj = read_u64(&energy_path)?;
+            let max_energy_uj = read_u64(&dir.join("max_energy_range_uj")).unwrap_or(u64::MAX);
+            self.zones.push(Zone {
+                kind,
+                energy_path,
+                max_energy_uj,
+                prev_uj,
+            });
+        }
+
+        if self.zones.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no RAPL zones"));
+        }
+        self.prev_time = Instant::now();
+        Ok(())
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        let now = Instant::now();
+        let interval_s = now.duration_since(self.prev_time).as_secs_f64();
+        self.prev_time = now;
+        if interval_s <= 0.0 {
+            return;
+        }
+
+        let mut package = 0.0;
+        let mut core = 0.0;
+        let mut uncore = 0.0;
+        let mut dram = 0.0;
+        let mut has_core = false;
+
+        for zone in &mut self.zones {
+            let Ok(energy_uj) = read_u64(&zone.energy_path) else {
+                continue;
+            };
+            let delta_uj = energy_delta(zone.prev_uj, energy_uj, zone.max_energy_uj);
+            zone.prev_uj = energy_uj;
+
+            let watts = delta_uj as f64 / 1_000_000.0 / interval_s;
+            match zone.kind {
+                ZoneKind::Package => package += watts,
+                ZoneKind::Core => {
+                    core += watts;
+                    has_core = true;
+                }
+                ZoneKind::Uncore => uncore += watts,
+                ZoneKind::Dram => dram += watts,
+            }
+        }
+
+        metrics.power.package_watts = package;
+        // Without a core subzone (e.g. AMD) the package is the best CPU figure
+        metrics.power.cpu_watts = if has_core { core } else { package };
+        metrics.power.gpu_watts = uncore;
+        metrics.power.dram_watts = dram;
+    }
+}
+
+/// Energy consumed between two counter readings. The counter wraps back to
+/// zero after `max_uj`.
+fn energy_delta(prev_uj: u64, now_uj: u64, max_uj: u64) -> u64 {
+    if now_uj >= prev_uj {
+        now_uj - prev_uj
+    } else {
+        max_uj.saturating_sub(prev_uj) + now_uj
+    }
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u64(path: &Path) -> io::Result<u64> {
+    fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// A throwaway powercap tree, removed on drop.
+    struct FakePowercap(PathBuf);
+
+    impl FakePowercap {
+        fn new(test: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("macy-rapl-{}-{}", test, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            FakePowercap(root)
+        }
+
+        fn zone(&self, dir: &str, name: &str, energy_uj: u64, max_uj: Option<u64>) {
+            let dir = self.0.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("name"), format!("{}\n", name)).unwrap();
+            fs::write(dir.join("energy_uj"), format!("{}\n", energy_uj)).unwrap();
+            if let Some(max_uj) = max_uj {
+                fs::write(dir.join("max_energy_range_uj"), format!("{}\n", max_uj)).unwrap();
+            }
+        }
+
+        fn set_energy(&self, dir: &str, energy_uj: u64) {
+            fs::write(self.0.join(dir).join("energy_uj"), format!("{}\n", energy_uj)).unwrap();
+        }
+    }
+
+    impl Drop for FakePowercap {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Sample as if exactly one second had passed since the last reading.
+    fn sample_after_one_second(source: &mut RaplSource) -> Metrics {
+        let mut metrics = Metrics::default();
+        source.prev_time = Instant::now() - Duration::from_secs(1);
+        source.sample(&mut metrics);
+        metrics
+    }
+
+    fn assert_watts(actual: f64, expected: f64) {
+        // The interval is a little over a second by the time it's measured
+        assert!((actual - expected).abs() < expected * 0.05 + 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn zones_map_to_power_fields() {
+        let tree = FakePowercap::new("zones");
+        tree.zone("intel-rapl:0", "package-0", 1_000_000, Some(u64::MAX));
+        tree.zone("intel-rapl:0:0", "core", 1_000_000, None);
+        tree.zone("intel-rapl:0:1", "uncore", 1_000_000, None);
+        tree.zone("intel-rapl:0:2", "dram", 1_000_000, None);
+        tree.zone("intel-rapl:1", "psys", 1_000_000, None);
+        tree.zone("other", "package-1", 1_000_000, None);
+
+        let mut source = RaplSource::with_root(&tree.0);
+        source.init().unwrap();
+        let kinds: Vec<ZoneKind> = source.zones.iter().map(|z| z.kind).collect();
+        assert_eq!(kinds, [ZoneKind::Package, ZoneKind::Core, ZoneKind::Uncore, ZoneKind::Dram]);
+
+        tree.set_energy("intel-rapl:0", 11_000_000);
+        tree.set_energy("intel-rapl:0:0", 7_000_000);
+        tree.set_energy("intel-rapl:0:1", 2_000_000);
+        tree.set_energy("intel-rapl:0:2", 3_000_000);
+        let metrics = sample_after_one_second(&mut source);
+
+        assert_watts(metrics.power.package_watts, 10.0);
+        assert_watts(metrics.power.cpu_watts, 6.0);
+        assert_watts(metrics.power.gpu_watts, 1.0);
+        assert_watts(metrics.power.dram_watts, 2.0);
+    }
+
+    #[test]
+    fn package_stands_in_for_cpu_without_a_core_zone() {
+        let tree = FakePowercap::new("package-only");
+        tree.zone("intel-rapl:0", "package-0", 0, None);
+
+        let mut source = RaplSource::with_root(&tree.0);
+        source.init().unwrap();
+        tree.set_energy("intel-rapl:0", 15_000_000);
+        let metrics = sample_after_one_second(&mut source);
+
+        assert_watts(metrics.power.package_watts, 15.0);
+        assert_watts(metrics.power.cpu_watts, 15.0);
+    }
+
+    #[test]
+    fn counter_wraps_at_max_energy_range() {
+        let tree = FakePowercap::new("wrap");
+        tree.zone("intel-rapl:0", "package-0", 900_000, Some(1_000_000));
+
+        let mut source = RaplSource::with_root(&tree.0);
+        source.init().unwrap();
+        tree.set_energy("intel-rapl:0", 100_000);
+        let metrics = sample_after_one_second(&mut source);
+
+        assert_watts(metrics.power.package_watts, 0.2);
+    }
+
+    #[test]
+    fn no_zones_fails_init() {
+        let tree = FakePowercap::new("empty");
+        tree.zone("intel-rapl:1", "psys", 0, None);
+        assert!(RaplSource::with_root(&tree.0).init().is_err());
+    }
+
+    #[test]
+    fn energy_delta_cases() {
+        assert_eq!(energy_delta(100, 250, 1_000), 150);
+        assert_eq!(energy_delta(900, 100, 1_000), 200);
+        assert_eq!(energy_delta(5, 5, 1_000), 0);
+    }
+}
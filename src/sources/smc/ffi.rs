@@ -0,0 +1,218 @@
+use super::{average_temp, classify_temp_key, decode, fourcc, fourcc_string, Fan, Sensor};
+use crate::metrics::Metrics;
+use crate::sources::iokit::{Connection, RegistryEntry};
+use crate::sources::MetricSource;
+use std::collections::HashMap;
+use std::io;
+
+/// AppleSMC external method that takes and returns an `SmcKeyData`.
+const KERNEL_INDEX_SMC: u32 = 2;
+
+// Commands, passed in `SmcKeyData::data8`
+const CMD_READ_BYTES: u8 = 5;
+const CMD_READ_INDEX: u8 = 8;
+const CMD_READ_KEYINFO: u8 = 9;
+
+// The SMCKeyData_t layout from AppleSMC, 80 bytes in total
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct KeyDataVers {
+    major: u8,
+    minor: u8,
+    build: u8,
+    reserved: u8,
+    release: u16,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct PLimitData {
+    version: u16,
+    length: u16,
+    cpu_plimit: u32,
+    gpu_plimit: u32,
+    mem_plimit: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct KeyInfo {
+    data_size: u32,
+    data_type: u32,
+    data_attributes: u8,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+struct SmcKeyData {
+    key: u32,
+    vers: KeyDataVers,
+    p_limit_data: PLimitData,
+    key_info: KeyInfo,
+    result: u8,
+    status: u8,
+    data8: u8,
+    data32: u32,
+    bytes: [u8; 32],
+}
+
+const _: () = assert!(std::mem::size_of::<SmcKeyData>() == 80);
+
+/// A connection to the AppleSMC user client.
+struct Smc {
+    connection: Connection,
+    /// Key info never changes, so it's looked up once per key.
+    key_info: HashMap<u32, KeyInfo>,
+}
+
+impl Smc {
+    fn open() -> io::Result<Self> {
+        let service = RegistryEntry::matching("AppleSMC")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no AppleSMC service"))?;
+        Ok(Smc {
+            connection: service.open()?,
+            key_info: HashMap::new(),
+        })
+    }
+
+    fn call(&self, input: &SmcKeyData) -> io::Result<SmcKeyData> {
+        let mut output = SmcKeyData::default();
+        let kr = unsafe {
+            self.connection
+                .call_struct_method(KERNEL_INDEX_SMC, input, &mut output)
+        };
+        if kr != 0 {
+            return Err(io::Error::other(format!("SMC call failed: {:#x}", kr)));
+        }
+        // A non-zero result is the SMC's own error, e.g. key not found
+        if output.result != 0 {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "SMC key not readable"));
+        }
+        Ok(output)
+    }
+
+    fn info(&mut self, key: u32) -> io::Result<KeyInfo> {
+        if let Some(info) = self.key_info.get(&key) {
+            return Ok(*info);
+        }
+        let output = self.call(&SmcKeyData {
+            key,
+            data8: CMD_READ_KEYINFO,
+            ..SmcKeyData::default()
+        })?;
+        self.key_info.insert(key, output.key_info);
+        Ok(output.key_info)
+    }
+
+    /// Read and decode a key. `None` if it's missing or of a type we
+    /// don't decode.
+    fn read(&mut self, key: &str) -> Option<f64> {
+        self.read_key(fourcc(key))
+    }
+
+    fn read_key(&mut self, key: u32) -> Option<f64> {
+        let info = self.info(key).ok()?;
+        let output = self
+            .call(&SmcKeyData {
+                key,
+                key_info: info,
+                data8: CMD_READ_BYTES,
+                ..SmcKeyData::default()
+            })
+            .ok()?;
+        let size = (info.data_size as usize).min(output.bytes.len());
+        decode(&fourcc_string(info.data_type), &output.bytes[..size])
+    }
+
+    /// The key at `index` in the SMC's key table.
+    fn key_at(&self, index: u32) -> io::Result<u32> {
+        let output = self.call(&SmcKeyData {
+            data8: CMD_READ_INDEX,
+            data32: index,
+            ..SmcKeyData::default()
+        })?;
+        Ok(output.key)
+    }
+}
+
+/// CPU/GPU die temperatures, fans and system power from the SMC.
+///
+/// The temperature keys differ between chips, so `init` walks the whole
+/// key table once and keeps the die sensors it finds.
+pub struct SmcSource {
+    smc: Option<Smc>,
+    cpu_keys: Vec<u32>,
+    gpu_keys: Vec<u32>,
+    fans: usize,
+}
+
+impl SmcSource {
+    pub fn new() -> Self {
+        Self {
+            smc: None,
+            cpu_keys: Vec::new(),
+            gpu_keys: Vec::new(),
+            fans: 0,
+        }
+    }
+}
+
+impl MetricSource for SmcSource {
+    fn describe(&self) -> &str {
+        "thermal (AppleSMC)"
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        let mut smc = Smc::open()?;
+
+        let count = smc.read("#KEY").unwrap_or(0.0) as u32;
+        for index in 0..count {
+            let Ok(key) = smc.key_at(index) else {
+                continue;
+            };
+            let Some(sensor) = classify_temp_key(&fourcc_string(key)) else {
+                continue;
+            };
+            // Only keep sensors that decode to a value
+            if smc.read_key(key).is_none() {
+                continue;
+            }
+            match sensor {
+                Sensor::Cpu => self.cpu_keys.push(key),
+                Sensor::Gpu => self.gpu_keys.push(key),
+            }
+        }
+        self.fans = smc.read("FNum").unwrap_or(0.0) as usize;
+
+        if self.cpu_keys.is_empty() && self.gpu_keys.is_empty() && self.fans == 0 {
+            return Err(io::Error::other("no SMC temperature sensors or fans"));
+        }
+        self.smc = Some(smc);
+        Ok(())
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        let Some(smc) = &mut self.smc else {
+            return;
+        };
+
+        let cpu: Vec<f64> = self.cpu_keys.iter().filter_map(|&k| smc.read_key(k)).collect();
+        let gpu: Vec<f64> = self.gpu_keys.iter().filter_map(|&k| smc.read_key(k)).collect();
+        metrics.thermal.cpu_temp_c = average_temp(&cpu);
+        metrics.thermal.gpu_temp_c = average_temp(&gpu);
+
+        metrics.thermal.fans = (0..self.fans)
+            .map(|i| Fan {
+                rpm: smc.read(&format!("F{}Ac", i)).unwrap_or(0.0),
+                min_rpm: smc.read(&format!("F{}Mn", i)).unwrap_or(0.0),
+                max_rpm: smc.read(&format!("F{}Mx", i)).unwrap_or(0.0),
+            })
+            .collect();
+
+        // Whole-system power as measured at the DC input
+        if let Some(watts) = smc.read("PSTR") {
+            metrics.power.system_watts = watts;
+        }
+    }
+}
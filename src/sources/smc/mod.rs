@@ -0,0 +1,187 @@
+// Off macOS only the model types are used, for recordings.
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+#[cfg(target_os = "macos")]
+mod ffi;
+
+#[cfg(target_os = "macos")]
+pub use ffi::SmcSource;
+
+use super::thermal_pressure::ThermalPressure;
+use serde::{Deserialize, Serialize};
+
+/// Temperatures and fans from the SMC.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThermalMetrics {
+    /// Average of the CPU die sensors, 0 if there are none.
+    pub cpu_temp_c: f64,
+    /// Average of the GPU die sensors, 0 if there are none.
+    pub gpu_temp_c: f64,
+    pub fans: Vec<Fan>,
+    /// The OS thermal pressure level, which is what actually throttles.
+    pub pressure: ThermalPressure,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fan {
+    pub rpm: f64,
+    pub min_rpm: f64,
+    pub max_rpm: f64,
+}
+
+/// Which die a temperature key measures, by its prefix: `Tp`/`Te` are the
+/// P- and E-cluster sensors, `Tg` the GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensor {
+    Cpu,
+    Gpu,
+}
+
+pub fn classify_temp_key(key: &str) -> Option<Sensor> {
+    if key.starts_with("Tp") || key.starts_with("Te") {
+        Some(Sensor::Cpu)
+    } else if key.starts_with("Tg") {
+        Some(Sensor::Gpu)
+    } else {
+        None
+    }
+}
+
+/// Pack a four-character SMC key or type into the u32 the SMC uses.
+pub fn fourcc(code: &str) -> u32 {
+    code.bytes().take(4).fold(0, |acc, b| (acc << 8) | b as u32)
+}
+
+/// Unpack a u32 key or type into its four characters.
+pub fn fourcc_string(code: u32) -> String {
+    code.to_be_bytes().iter().map(|&b| b as char).collect()
+}
+
+/// Decode an SMC value of the given type. Returns `None` for types we
+/// don't read or data too short for the type.
+pub fn decode(data_type: &str, bytes: &[u8]) -> Option<f64> {
+    match data_type {
+        "flt " => decode_flt(bytes),
+        "sp78" => decode_sp78(bytes),
+        "fpe2" => decode_fpe2(bytes),
+        "ui8 " | "ui16" | "ui32" => decode_uint(bytes, data_type_size(data_type)?),
+        _ => None,
+    }
+}
+
+fn data_type_size(data_type: &str) -> Option<usize> {
+    match data_type {
+        "ui8 " => Some(1),
+        "ui16" => Some(2),
+        "ui32" => Some(4),
+        _ => None,
+    }
+}
+
+/// 32-bit float. Apple Silicon SMCs store it little-endian, unlike the
+/// big-endian integer and fixed-point types.
+pub fn decode_flt(bytes: &[u8]) -> Option<f64> {
+    let raw: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+    Some(f32::from_le_bytes(raw) as f64)
+}
+
+/// Signed 8.8 fixed point, big-endian (Intel-era temperatures).
+pub fn decode_sp78(bytes: &[u8]) -> Option<f64> {
+    let raw: [u8; 2] = bytes.get(..2)?.try_into().ok()?;
+    Some(i16::from_be_bytes(raw) as f64 / 256.0)
+}
+
+/// Unsigned 14.2 fixed point, big-endian (Intel-era fan speeds).
+pub fn decode_fpe2(bytes: &[u8]) -> Option<f64> {
+    let raw: [u8; 2] = bytes.get(..2)?.try_into().ok()?;
+    Some(u16::from_be_bytes(raw) as f64 / 4.0)
+}
+
+/// Big-endian unsigned integer of `size` bytes.
+pub fn decode_uint(bytes: &[u8], size: usize) -> Option<f64> {
+    let raw = bytes.get(..size)?;
+    Some(raw.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64) as f64)
+}
+
+/// Average of the readings in a plausible die temperature range. Unused
+/// sensors read 0 or garbage on some machines.
+pub fn average_temp(readings: &[f64]) -> f64 {
+    let valid: Vec<f64> = readings
+        .iter()
+        .copied()
+        .filter(|t| *t > 0.0 && *t < 150.0)
+        .collect();
+    if valid.is_empty() {
+        return 0.0;
+    }
+    valid.iter().sum::<f64>() / valid.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flt_is_little_endian() {
+        assert_eq!(decode("flt ", &42.5f32.to_le_bytes()), Some(42.5));
+        assert_eq!(decode_flt(&[0, 0, 0x28, 0x42, 0xff]), Some(42.0));
+        assert_eq!(decode_flt(&[0, 0, 0x28]), None);
+    }
+
+    #[test]
+    fn fixed_point_is_big_endian() {
+        // 0x2d80 = 45.5 in 8.8
+        assert_eq!(decode("sp78", &[0x2d, 0x80]), Some(45.5));
+        assert_eq!(decode_sp78(&[0xff, 0x00]), Some(-1.0));
+        // 0x1f40 = 2000 in 14.2
+        assert_eq!(decode("fpe2", &[0x1f, 0x40]), Some(2000.0));
+        assert_eq!(decode_sp78(&[0x2d]), None);
+        assert_eq!(decode_fpe2(&[]), None);
+    }
+
+    #[test]
+    fn unsigned_integers_are_big_endian() {
+        assert_eq!(decode("ui8 ", &[7]), Some(7.0));
+        assert_eq!(decode("ui16", &[0x12, 0x34]), Some(4660.0));
+        assert_eq!(decode("ui32", &[0, 1, 0, 0]), Some(65536.0));
+        // Only the type's size is read
+        assert_eq!(decode("ui8 ", &[7, 9]), Some(7.0));
+        assert_eq!(decode("ui16", &[0x12]), None);
+        assert_eq!(decode("ui32", &[0, 1, 0]), None);
+    }
+
+    #[test]
+    fn unknown_types_are_not_decoded() {
+        assert_eq!(decode("ch8*", b"abcd"), None);
+        assert_eq!(decode("ui64", &[0; 8]), None);
+        assert_eq!(decode("", &[]), None);
+    }
+
+    #[test]
+    fn temperature_keys_by_prefix() {
+        assert_eq!(classify_temp_key("Tp09"), Some(Sensor::Cpu));
+        assert_eq!(classify_temp_key("Te05"), Some(Sensor::Cpu));
+        assert_eq!(classify_temp_key("Tg0f"), Some(Sensor::Gpu));
+        assert_eq!(classify_temp_key("TB0T"), None);
+        assert_eq!(classify_temp_key("F0Ac"), None);
+        assert_eq!(classify_temp_key("T"), None);
+    }
+
+    #[test]
+    fn fourcc_round_trip() {
+        assert_eq!(fourcc("#KEY"), 0x234b_4559);
+        assert_eq!(fourcc_string(fourcc("flt ")), "flt ");
+        assert_eq!(fourcc_string(fourcc("TC0P")), "TC0P");
+        // Only the first four characters count
+        assert_eq!(fourcc("ui16x"), fourcc("ui16"));
+    }
+
+    #[test]
+    fn implausible_temperatures_are_ignored() {
+        assert_eq!(average_temp(&[40.0, 0.0, 60.0, 200.0, -3.0]), 50.0);
+        assert_eq!(average_temp(&[0.0]), 0.0);
+        assert_eq!(average_temp(&[]), 0.0);
+    }
+}
@@ -0,0 +1,75 @@
+use std::ffi::{c_char, c_int};
+use std::io;
+
+use super::ThermalPressure;
+use crate::sources::MetricSource;
+use crate::metrics::Metrics;
+
+/// `kOSThermalNotificationPressureLevelName`. Its state is the current
+/// `OSThermalPressureLevel`, the same signal behind
+/// `NSProcessInfo.thermalState`.
+const THERMAL_PRESSURE_NOTIFICATION: &std::ffi::CStr = c"com.apple.system.thermalpressurelevel";
+
+const NOTIFY_STATUS_OK: u32 = 0;
+
+extern "C" {
+    fn notify_register_check(name: *const c_char, out_token: *mut c_int) -> u32;
+    fn notify_get_state(token: c_int, state: *mut u64) -> u32;
+    fn notify_cancel(token: c_int) -> u32;
+}
+
+/// The OS thermal pressure level, read from the notification state without
+/// waiting for notifications to arrive.
+pub struct ThermalPressureSource {
+    token: Option<c_int>,
+    /// The last level we recognized, kept over levels we don't.
+    pressure: ThermalPressure,
+}
+
+impl ThermalPressureSource {
+    pub fn new() -> Self {
+        Self {
+            token: None,
+            pressure: ThermalPressure::default(),
+        }
+    }
+
+    fn level(&self) -> Option<u64> {
+        let token = self.token?;
+        let mut state: u64 = 0;
+        let status = unsafe { notify_get_state(token, &mut state) };
+        (status == NOTIFY_STATUS_OK).then_some(state)
+    }
+}
+
+impl MetricSource for ThermalPressureSource {
+    fn describe(&self) -> &str {
+        "thermal pressure (notify)"
+    }
+
+    fn init(&mut self) -> io::Result<()> {
+        let mut token: c_int = 0;
+        let status =
+            unsafe { notify_register_check(THERMAL_PRESSURE_NOTIFICATION.as_ptr(), &mut token) };
+        if status != NOTIFY_STATUS_OK {
+            return Err(io::Error::other(format!("notify_register_check failed: {}", status)));
+        }
+        self.token = Some(token);
+        Ok(())
+    }
+
+    fn sample(&mut self, metrics: &mut Metrics) {
+        if let Some(pressure) = self.level().and_then(ThermalPressure::from_level) {
+            self.pressure = pressure;
+        }
+        metrics.thermal.pressure = self.pressure;
+    }
+}
+
+impl Drop for ThermalPressureSource {
+    fn drop(&mut self) {
+        if let Some(token) = self.token {
+            unsafe { notify_cancel(token) };
+        }
+    }
+}
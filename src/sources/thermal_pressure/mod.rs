@@ -0,0 +1,94 @@
+// The level is recorded and shown everywhere, but only macOS reports it.
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+#[cfg(target_os = "macos")]
+mod ffi;
+
+#[cfg(target_os = "macos")]
+pub use ffi::ThermalPressureSource;
+
+use serde::{Deserialize, Serialize};
+
+/// The OS thermal pressure level (`OSThermalPressureLevel`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThermalPressure {
+    #[default]
+    Nominal,
+    Moderate,
+    Heavy,
+    Trapping,
+    Sleeping,
+}
+
+impl ThermalPressure {
+    /// From the value posted with the thermal pressure notification.
+    /// `None` for levels newer than this list.
+    pub fn from_level(level: u64) -> Option<Self> {
+        match level {
+            0 => Some(ThermalPressure::Nominal),
+            1 => Some(ThermalPressure::Moderate),
+            2 => Some(ThermalPressure::Heavy),
+            3 => Some(ThermalPressure::Trapping),
+            4 => Some(ThermalPressure::Sleeping),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThermalPressure::Nominal => "nominal",
+            ThermalPressure::Moderate => "moderate",
+            ThermalPressure::Heavy => "heavy",
+            ThermalPressure::Trapping => "trapping",
+            ThermalPressure::Sleeping => "sleeping",
+        }
+    }
+}
+
+/// A change of thermal pressure, with the GPU frequency at that moment so
+/// throttling shows up next to its cause.
+#[derive(Debug, Clone)]
+pub struct PressureChange {
+    /// Wall clock (or recording) time of the sample, as shown to the user.
+    pub time: String,
+    pub from: ThermalPressure,
+    pub to: ThermalPressure,
+    pub gpu_freq_mhz: f64,
+}
+
+impl std::fmt::Display for PressureChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}, GPU {:.0} MHz",
+            self.time,
+            self.from.label(),
+            self.to.label(),
+            self.gpu_freq_mhz
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_levels() {
+        assert_eq!(ThermalPressure::from_level(0), Some(ThermalPressure::Nominal));
+        assert_eq!(ThermalPressure::from_level(2), Some(ThermalPressure::Heavy));
+        assert_eq!(ThermalPressure::from_level(4), Some(ThermalPressure::Sleeping));
+        assert_eq!(ThermalPressure::from_level(5), None);
+        assert_eq!(ThermalPressure::from_level(u64::MAX), None);
+    }
+
+    #[test]
+    fn levels_round_trip_through_their_number() {
+        // CSV and the exporter write the level as its number
+        for level in 0..5 {
+            let pressure = ThermalPressure::from_level(level).unwrap();
+            assert_eq!(pressure as u8 as u64, level);
+        }
+    }
+}
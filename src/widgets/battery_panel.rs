@@ -0,0 +1,86 @@
+use super::History;
+use crate::sources::battery::{BatteryInfo, TimeRemaining};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline};
+use std::time::Duration;
+
+/// `amperage_a` is the current averaged over the history window, used for
+/// the time estimate.
+pub fn render(
+    frame: &mut ratatui::Frame,
+    area: Rect,
+    battery: &BatteryInfo,
+    amperage_a: f64,
+    history: &History,
+) {
+    let charge = battery.charge_percent();
+    let border_color = if battery.external_power {
+        Color::Green
+    } else if charge < 20.0 {
+        Color::Red
+    } else {
+        Color::Yellow
+    };
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Battery  {:.0}% ", charge))
+        .border_style(Style::default().fg(border_color));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if inner.height < 2 {
+        return;
+    }
+
+    let label = Style::default().fg(Color::DarkGray);
+    let watts = battery.watts().abs();
+
+    let state = match battery.time_remaining(amperage_a) {
+        Some(TimeRemaining::ToEmpty(time)) => vec![
+            Span::raw(format!("Discharging {:.1}W", watts)),
+            Span::styled(format!("   {} left", format_duration(time)), label),
+        ],
+        Some(TimeRemaining::ToFull(time)) => vec![
+            Span::raw(format!("Charging {:.1}W", watts)),
+            Span::styled(format!("   {} to full", format_duration(time)), label),
+        ],
+        None if battery.external_power => vec![Span::raw("On power, not charging")],
+        None => vec![Span::raw("Idle")],
+    };
+
+    let mut health = vec![
+        Span::styled("Health ", label),
+        Span::raw(format!("{:.0}%", battery.health_percent())),
+        Span::styled(format!("   {} cycles", battery.cycle_count), label),
+    ];
+    if battery.temperature_c > 0.0 {
+        health.push(Span::styled(format!("   {:.0}°C", battery.temperature_c), label));
+    }
+
+    let text = vec![Line::from(state), Line::from(health)];
+
+    // Split inner area: text on top, charge graph on bottom
+    let text_height = (text.len() as u16).min(inner.height - 1);
+    let chunks = Layout::vertical([
+        Constraint::Length(text_height),
+        Constraint::Min(1),
+    ])
+    .split(inner);
+
+    frame.render_widget(Paragraph::new(text), chunks[0]);
+
+    let sparkline = Sparkline::default()
+        .data(history.data())
+        .max(100)
+        .style(Style::default().fg(border_color));
+
+    frame.render_widget(sparkline, chunks[1]);
+}
+
+/// Format a duration as h:mm.
+fn format_duration(time: Duration) -> String {
+    let mins = time.as_secs() / 60;
+    format!("{}:{:02}", mins / 60, mins % 60)
+}
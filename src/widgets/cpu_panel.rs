@@ -1,19 +1,110 @@
 use super::History;
+use crate::soc::SocInfo;
+use crate::sources::cpu::CpuUsage;
+use crate::sources::ioreport::CpuClusterMetrics;
 use ratatui::prelude::*;
-use ratatui::widgets::{Block, Borders, Sparkline};
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline};
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub fn render(
+    frame: &mut ratatui::Frame,
+    area: Rect,
+    cpu: &CpuUsage,
+    cluster_stats: &[CpuClusterMetrics],
+    soc: &SocInfo,
+    history: &History,
+) {
+    let clusters = clusters(&cpu.per_core, soc);
+
+    let mut title = format!(" CPU  {:.0}% ", cpu.overall_percent);
+    if clusters.len() > 1 {
+        for (label, cores) in &clusters {
+            title.push_str(&format!(" {} {:.0}%", label, average(cores)));
+            let freq = cluster_stats
+                .iter()
+                .find(|c| c.name == *label)
+                .map_or(0.0, |c| c.freq_mhz);
+            if freq > 0.0 {
+                title.push_str(&format!(" @ {:.0} MHz", freq));
+            }
+            title.push(' ');
+        }
+    }
 
-pub fn render(frame: &mut ratatui::Frame, area: Rect, usage: f64, history: &History) {
-    let title = format!(" CPU  {:.0}% ", usage);
     let block = Block::default()
         .borders(Borders::ALL)
         .title(title)
         .border_style(Style::default().fg(Color::Blue));
 
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // One heatmap row per cluster below the graph, if there's room for both
+    let rows = if inner.height as usize > clusters.len() { clusters.len() } else { 0 };
+    let chunks = Layout::vertical([
+        Constraint::Min(1),
+        Constraint::Length(rows as u16),
+    ])
+    .split(inner);
+
     let sparkline = Sparkline::default()
-        .block(block)
         .data(history.data())
         .max(100)
         .style(Style::default().fg(Color::Cyan));
 
-    frame.render_widget(sparkline, area);
+    frame.render_widget(sparkline, chunks[0]);
+
+    if rows > 0 {
+        let lines: Vec<Line> = clusters
+            .iter()
+            .map(|(label, cores)| heatmap_row(label, cores, chunks[1].width))
+            .collect();
+        frame.render_widget(Paragraph::new(lines), chunks[1]);
+    }
+}
+
+/// Split per-core usage into E- and P-clusters. macOS numbers the
+/// efficiency cores first. Without an E/P split everything is one group.
+fn clusters<'a>(per_core: &'a [f64], soc: &SocInfo) -> Vec<(&'static str, &'a [f64])> {
+    let e = soc.e_cores as usize;
+    if e > 0 && per_core.len() > e {
+        vec![("E", &per_core[..e]), ("P", &per_core[e..])]
+    } else {
+        vec![("", per_core)]
+    }
+}
+
+fn average(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// One cell per core: bar height and color follow the core's usage.
+fn heatmap_row(label: &str, cores: &[f64], width: u16) -> Line<'static> {
+    let mut spans = Vec::with_capacity(cores.len() + 1);
+    let mut used = 0;
+    if !label.is_empty() {
+        spans.push(Span::styled(format!("{} ", label), Style::default().fg(Color::DarkGray)));
+        used += 2;
+    }
+
+    // Drop the gap between cells when the cores wouldn't fit otherwise
+    let gap = if used + cores.len() * 2 <= width as usize { " " } else { "" };
+
+    for &usage in cores {
+        let idx = ((usage / 100.0 * BARS.len() as f64) as usize).min(BARS.len() - 1);
+        let color = if usage >= 80.0 {
+            Color::Red
+        } else if usage >= 50.0 {
+            Color::Yellow
+        } else {
+            Color::Green
+        };
+        spans.push(Span::styled(format!("{}{}", BARS[idx], gap), Style::default().fg(color)));
+    }
+
+    Line::from(spans)
 }
@@ -0,0 +1,87 @@
+use super::{format_rate, format_size, History};
+use crate::sources::disk::DiskDevice;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Sparkline, Table};
+
+/// A table of per-disk bytes and operations per second and average
+/// latency, over a graph of the combined read + write throughput in
+/// `history`.
+pub fn render(frame: &mut ratatui::Frame, area: Rect, disks: &[DiskDevice], history: &History) {
+    let read: f64 = disks.iter().map(|d| d.read_bytes_per_sec).sum();
+    let write: f64 = disks.iter().map(|d| d.write_bytes_per_sec).sum();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Disk  R {}  W {} ", format_rate(read), format_rate(write)))
+        .border_style(Style::default().fg(Color::LightYellow));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if disks.is_empty() {
+        let text = Paragraph::new("No disks").style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(text, inner);
+        return;
+    }
+    if inner.height < 3 {
+        return;
+    }
+
+    // Split inner area: header and one row per disk on top, graph on bottom
+    let table_height = (disks.len() as u16 + 1).min(inner.height - 1);
+    let chunks = Layout::vertical([
+        Constraint::Length(table_height),
+        Constraint::Min(1),
+    ])
+    .split(inner);
+
+    let heading = |name: &'static str| Line::from(name).right_aligned();
+    let header = Row::new(vec![
+        Line::from("Device"),
+        heading("Read"),
+        heading("Write"),
+        heading("R op"),
+        heading("W op"),
+        heading("ms"),
+    ])
+    .style(Style::default().fg(Color::DarkGray));
+
+    let rows: Vec<Row> = disks
+        .iter()
+        .map(|disk| {
+            Row::new(vec![
+                Line::from(disk.name.clone()),
+                Line::from(format_size(disk.read_bytes_per_sec))
+                    .right_aligned()
+                    .style(Style::default().fg(Color::Green)),
+                Line::from(format_size(disk.write_bytes_per_sec))
+                    .right_aligned()
+                    .style(Style::default().fg(Color::Cyan)),
+                Line::from(format!("{:.0}", disk.read_iops)).right_aligned(),
+                Line::from(format!("{:.0}", disk.write_iops)).right_aligned(),
+                Line::from(format!("{:.1}", disk.latency_ms)).right_aligned(),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Fill(1),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(4),
+        ],
+    )
+    .header(header);
+
+    frame.render_widget(table, chunks[0]);
+
+    let sparkline = Sparkline::default()
+        .data(history.data())
+        .style(Style::default().fg(Color::LightYellow));
+
+    frame.render_widget(sparkline, chunks[1]);
+}
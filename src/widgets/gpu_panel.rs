@@ -1,9 +1,19 @@
-use super::History;
+use super::{format_binary, History};
+use crate::soc::SocInfo;
 use crate::sources::ioreport::GpuMetrics;
 use ratatui::prelude::*;
-use ratatui::widgets::{Block, Borders, Sparkline};
+use ratatui::widgets::{Bar, BarChart, BarGroup, Block, Borders, Paragraph, Sparkline};
 
-pub fn render(frame: &mut ratatui::Frame, area: Rect, gpu: &GpuMetrics, history: &History) {
+/// Draw the GPU panel: the utilization graph, or with `histogram` set, the
+/// time spent in each P-state.
+pub fn render(
+    frame: &mut ratatui::Frame,
+    area: Rect,
+    gpu: &GpuMetrics,
+    soc: &SocInfo,
+    history: &History,
+    histogram: bool,
+) {
     let title = if gpu.freq_mhz > 0.0 {
         format!(" GPU  {:.0}% @ {:.0} MHz ", gpu.utilization, gpu.freq_mhz)
     } else {
@@ -15,11 +25,108 @@ pub fn render(frame: &mut ratatui::Frame, area: Rect, gpu: &GpuMetrics, history:
         .title(title)
         .border_style(Style::default().fg(Color::Magenta));
 
-    let sparkline = Sparkline::default()
-        .block(block)
-        .data(history.data())
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // Accelerator statistics below the graph, when the driver reports them
+    let lines = stats_lines(gpu);
+    let rows = if inner.height as usize > lines.len() { lines.len() } else { 0 };
+    let chunks = Layout::vertical([
+        Constraint::Min(1),
+        Constraint::Length(rows as u16),
+    ])
+    .split(inner);
+
+    if histogram {
+        render_histogram(frame, chunks[0], gpu, soc);
+    } else {
+        let sparkline = Sparkline::default()
+            .data(history.data())
+            .max(100)
+            .style(Style::default().fg(Color::LightMagenta));
+
+        frame.render_widget(sparkline, chunks[0]);
+    }
+
+    if rows > 0 {
+        frame.render_widget(Paragraph::new(lines), chunks[1]);
+    }
+}
+
+/// One bar per GPUPH state, labelled with its frequency where known.
+fn render_histogram(frame: &mut ratatui::Frame, area: Rect, gpu: &GpuMetrics, soc: &SocInfo) {
+    if gpu.residency.is_empty() {
+        let note = Paragraph::new("No P-state residency from IOReport")
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(note, area);
+        return;
+    }
+
+    let bars: Vec<Bar> = gpu
+        .residency
+        .iter()
+        .map(|state| {
+            // P1 is the lowest entry of the DVFS table
+            let freq = state
+                .name
+                .strip_prefix('P')
+                .and_then(|n| n.parse::<usize>().ok())
+                .and_then(|idx| soc.gpu_freqs.get(idx.checked_sub(1)?));
+            let label = match freq {
+                Some(mhz) => mhz.to_string(),
+                None => state.name.clone(),
+            };
+            let color = if state.name == "OFF" { Color::DarkGray } else { Color::Magenta };
+            Bar::default()
+                .value(state.percent.round() as u64)
+                .text_value(format!("{:.0}", state.percent))
+                .label(Line::from(label))
+                .style(Style::default().fg(color))
+        })
+        .collect();
+
+    // Fill the width, leaving a one-column gap between bars
+    let count = bars.len() as u16;
+    let bar_width = (area.width.saturating_sub(count.saturating_sub(1)) / count.max(1)).max(1);
+
+    let chart = BarChart::default()
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(bar_width)
+        .bar_gap(1)
         .max(100)
-        .style(Style::default().fg(Color::LightMagenta));
+        .value_style(Style::default().fg(Color::Black).bg(Color::Magenta));
+
+    frame.render_widget(chart, area);
+}
+
+fn stats_lines(gpu: &GpuMetrics) -> Vec<Line<'static>> {
+    let label = Style::default().fg(Color::DarkGray);
+    let mut lines = Vec::new();
+
+    if gpu.device_utilization > 0.0 || gpu.renderer_utilization > 0.0 || gpu.tiler_utilization > 0.0 {
+        let mut spans = Vec::new();
+        for (name, value) in [
+            ("Device", gpu.device_utilization),
+            ("Renderer", gpu.renderer_utilization),
+            ("Tiler", gpu.tiler_utilization),
+        ] {
+            spans.push(Span::styled(format!("{} ", name), label));
+            spans.push(Span::raw(format!("{:.0}%  ", value)));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    if gpu.alloc_memory_bytes > 0 {
+        let bytes = |value: u64| format_binary(value as f64, ["B", "KB", "MB", "GB"], " ");
+        lines.push(Line::from(vec![
+            Span::styled("Memory ", label),
+            Span::raw(format!(
+                "{} in use / {} allocated",
+                bytes(gpu.in_use_memory_bytes),
+                bytes(gpu.alloc_memory_bytes)
+            )),
+        ]));
+    }
 
-    frame.render_widget(sparkline, area);
+    lines
 }
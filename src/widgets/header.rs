@@ -1,11 +1,27 @@
+use super::pressure_color;
 use crate::soc::SocInfo;
+use crate::sources::thermal_pressure::ThermalPressure;
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, Borders};
 
-pub fn render(frame: &mut ratatui::Frame, area: Rect, soc: &SocInfo) {
+pub fn render(frame: &mut ratatui::Frame, area: Rect, soc: &SocInfo, pressure: ThermalPressure) {
+    // Make it obvious when core counts and tables couldn't be filled in
+    let title_style = if soc.unknown_chip {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    let title = Line::from(vec![
+        Span::styled(format!(" {} ", soc), title_style),
+        Span::styled(
+            format!(" thermal {} ", pressure.label()),
+            Style::default().fg(pressure_color(pressure)),
+        ),
+    ]);
+
     let block = Block::default()
         .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
-        .title(Line::from(format!(" {} ", soc)).left_aligned())
+        .title(title.left_aligned())
         .title(
             Line::from(format!(" macy v{} ", env!("CARGO_PKG_VERSION"))).right_aligned(),
         )
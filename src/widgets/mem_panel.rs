@@ -1,25 +1,134 @@
 use super::History;
-use crate::sources::memory::MemoryInfo;
+use crate::sources::memory::{MemoryInfo, MemoryPressure};
 use ratatui::prelude::*;
-use ratatui::widgets::{Block, Borders, Sparkline};
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline};
 
 pub fn render(frame: &mut ratatui::Frame, area: Rect, mem: &MemoryInfo, history: &History) {
-    let title = format!(
+    let mut title = format!(
         " Memory  {:.1} / {:.1} GB ",
         mem.used_gb(),
         mem.total_gb()
     );
+    if mem.swap_total_bytes > 0 {
+        title.push_str(&format!(
+            " Swap {:.1} / {:.1} GB ",
+            mem.swap_used_gb(),
+            mem.swap_total_gb()
+        ));
+    }
+    if mem.pressure != MemoryPressure::Normal {
+        title.push_str(&format!(" pressure {} ", mem.pressure.label()));
+    }
+
+    // The panel turns yellow, then red, as the kernel reports pressure
+    let color = match mem.pressure {
+        MemoryPressure::Normal => Color::Green,
+        MemoryPressure::Warn => Color::Yellow,
+        MemoryPressure::Critical => Color::Red,
+    };
 
     let block = Block::default()
         .borders(Borders::ALL)
         .title(title)
-        .border_style(Style::default().fg(Color::Green));
+        .border_style(Style::default().fg(color));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // Graph on top, then the stacked breakdown bar, its legend and the
+    // paging rates, as far as there's room
+    let breakdown = if mem.total_bytes == 0 {
+        0
+    } else {
+        inner.height.saturating_sub(2).min(3)
+    };
+    let chunks = Layout::vertical([
+        Constraint::Min(1),
+        Constraint::Length(breakdown),
+    ])
+    .split(inner);
 
     let sparkline = Sparkline::default()
-        .block(block)
         .data(history.data())
         .max(100)
         .style(Style::default().fg(Color::LightGreen));
 
-    frame.render_widget(sparkline, area);
+    frame.render_widget(sparkline, chunks[0]);
+
+    if breakdown > 0 {
+        let segments = segments(mem);
+        let lines = vec![
+            stacked_bar(&segments, mem.total_bytes, chunks[1].width),
+            legend(&segments),
+            paging(mem),
+        ];
+        frame.render_widget(Paragraph::new(lines), chunks[1]);
+    }
+}
+
+/// Activity Monitor's categories, in stacking order.
+fn segments(mem: &MemoryInfo) -> [(&'static str, Color, u64); 5] {
+    [
+        ("App", Color::LightGreen, mem.app_bytes),
+        ("Wired", Color::Yellow, mem.wired_bytes),
+        ("Compressed", Color::Magenta, mem.compressed_bytes),
+        ("Cached", Color::Blue, mem.cached_bytes),
+        ("Free", Color::DarkGray, mem.free_bytes),
+    ]
+}
+
+fn stacked_bar(segments: &[(&'static str, Color, u64)], total: u64, width: u16) -> Line<'static> {
+    let width = width as u64;
+    let mut spans = Vec::with_capacity(segments.len());
+    let mut filled = 0;
+    let mut cumulative = 0;
+    for &(_, color, bytes) in segments {
+        // Round cumulative edges so the segments always add up to the width
+        cumulative += bytes;
+        let edge = (cumulative.min(total) * width + total / 2) / total;
+        let cells = edge.saturating_sub(filled);
+        filled += cells;
+        spans.push(Span::styled(
+            "█".repeat(cells as usize),
+            Style::default().fg(color),
+        ));
+    }
+    Line::from(spans)
+}
+
+fn legend(segments: &[(&'static str, Color, u64)]) -> Line<'static> {
+    let mut spans = Vec::new();
+    for &(label, color, bytes) in segments {
+        if bytes == 0 {
+            continue;
+        }
+        spans.push(Span::styled("■ ", Style::default().fg(color)));
+        spans.push(Span::raw(format!(
+            "{} {:.1}G  ",
+            label,
+            bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+        )));
+    }
+    Line::from(spans)
+}
+
+fn paging(mem: &MemoryInfo) -> Line<'static> {
+    let label = Style::default().fg(Color::DarkGray);
+    let mut spans = Vec::new();
+    for (name, rate) in [
+        ("Pageins", mem.pageins_per_sec),
+        ("Pageouts", mem.pageouts_per_sec),
+        ("Compr", mem.compressions_per_sec),
+        ("Decompr", mem.decompressions_per_sec),
+    ] {
+        spans.push(Span::styled(format!("{} ", name), label));
+        // Sustained pageouts are the real sign of memory trouble
+        let style = if name == "Pageouts" && rate > 0.0 {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(format!("{:.0}/s  ", rate), style));
+    }
+    Line::from(spans)
 }
@@ -1,8 +1,16 @@
+pub mod battery_panel;
 pub mod cpu_panel;
+pub mod disk_panel;
 pub mod gpu_panel;
 pub mod header;
 pub mod mem_panel;
+pub mod network_panel;
 pub mod power_bar;
+pub mod process_table;
+pub mod thermal_panel;
+
+use crate::sources::thermal_pressure::ThermalPressure;
+use ratatui::style::Color;
 
 /// Ring buffer for sparkline history.
 #[derive(Clone)]
@@ -30,3 +38,54 @@ impl History {
         &self.data
     }
 }
+
+/// Format a byte rate with a binary unit, e.g. `1.2 MB/s`.
+pub fn format_rate(bytes_per_sec: f64) -> String {
+    format_binary(bytes_per_sec, ["B/s", "KB/s", "MB/s", "GB/s"], " ")
+}
+
+/// Short byte count for tables, e.g. `9.5M`.
+pub fn format_size(bytes: f64) -> String {
+    format_binary(bytes, ["", "K", "M", "G"], "")
+}
+
+/// Scale `bytes` by 1024 up to the largest of `units` (bytes, K, M, G) and
+/// append the unit after `separator`: whole bytes, one decimal otherwise.
+pub fn format_binary(bytes: f64, units: [&str; 4], separator: &str) -> String {
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0}{}{}", value, separator, units[unit])
+    } else {
+        format!("{:.1}{}{}", value, separator, units[unit])
+    }
+}
+
+/// Color of a thermal pressure level; anything above nominal means the OS
+/// is throttling.
+pub fn pressure_color(pressure: ThermalPressure) -> Color {
+    match pressure {
+        ThermalPressure::Nominal => Color::Green,
+        ThermalPressure::Moderate => Color::Yellow,
+        _ => Color::Red,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_units() {
+        assert_eq!(format_rate(512.0), "512 B/s");
+        assert_eq!(format_rate(1536.0), "1.5 KB/s");
+        assert_eq!(format_rate(3.0 * 1024.0 * 1024.0 * 1024.0 * 1024.0), "3072.0 GB/s");
+        assert_eq!(format_size(9.5 * 1024.0 * 1024.0), "9.5M");
+        assert_eq!(format_size(0.0), "0");
+        assert_eq!(format_binary(1.5 * 1024.0 * 1024.0 * 1024.0, ["B", "KB", "MB", "GB"], " "), "1.5 GB");
+    }
+}
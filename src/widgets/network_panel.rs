@@ -0,0 +1,80 @@
+use super::{format_rate, History};
+use crate::sources::network::NetworkInterface;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline};
+
+/// The interface being shown and where it sits in the cycle.
+pub struct Selection<'a> {
+    pub interface: &'a NetworkInterface,
+    pub index: usize,
+    pub count: usize,
+}
+
+/// `show_all` is false while loopback and inactive interfaces are hidden.
+pub fn render(
+    frame: &mut ratatui::Frame,
+    area: Rect,
+    selection: Option<Selection<'_>>,
+    rx_history: &History,
+    tx_history: &History,
+    show_all: bool,
+) {
+    let filter = if show_all { "all" } else { "active" };
+    let title = match &selection {
+        Some(s) => format!(" Network  {}  {}/{} {} ", s.interface.name, s.index + 1, s.count, filter),
+        None => " Network ".to_string(),
+    };
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .border_style(Style::default().fg(Color::LightBlue));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let Some(selection) = selection else {
+        let text = format!("No {} interfaces", filter);
+        frame.render_widget(Paragraph::new(text).style(Style::default().fg(Color::DarkGray)), inner);
+        return;
+    };
+    if inner.height < 2 {
+        return;
+    }
+
+    let iface = selection.interface;
+    let label = Style::default().fg(Color::DarkGray);
+    let text = vec![
+        Line::from(vec![
+            Span::styled("RX ", Style::default().fg(Color::Green)),
+            Span::raw(format_rate(iface.rx_bytes_per_sec)),
+            Span::styled(format!("  {:.0} pkt/s", iface.rx_packets_per_sec), label),
+        ]),
+        Line::from(vec![
+            Span::styled("TX ", Style::default().fg(Color::Cyan)),
+            Span::raw(format_rate(iface.tx_bytes_per_sec)),
+            Span::styled(format!("  {:.0} pkt/s", iface.tx_packets_per_sec), label),
+        ]),
+    ];
+
+    // Split inner area: text on top, RX and TX graphs below
+    let text_height = (text.len() as u16).min(inner.height - 1);
+    let chunks = Layout::vertical([
+        Constraint::Length(text_height),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+    ])
+    .split(inner);
+
+    frame.render_widget(Paragraph::new(text), chunks[0]);
+
+    let rx = Sparkline::default()
+        .data(rx_history.data())
+        .style(Style::default().fg(Color::Green));
+    frame.render_widget(rx, chunks[1]);
+
+    let tx = Sparkline::default()
+        .data(tx_history.data())
+        .style(Style::default().fg(Color::Cyan));
+    frame.render_widget(tx, chunks[2]);
+}
@@ -21,31 +21,68 @@ pub fn render(
         return;
     }
 
+    let total = power.total_watts();
+    let mut text = vec![Line::from(parts(&[
+        ("CPU", Color::Cyan, power.cpu_watts),
+        ("GPU", Color::LightMagenta, power.gpu_watts),
+        ("ANE", Color::LightBlue, power.ane_watts),
+    ]))];
+
+    // Smaller domains only get a line when something is drawing power
+    let others = parts(&[
+        ("DRAM", Color::LightGreen, power.dram_watts),
+        ("ISP", Color::LightRed, power.isp_watts),
+        ("Media", Color::LightCyan, power.media_watts),
+    ]);
+    if !others.is_empty() {
+        text.push(Line::from(others));
+    }
+
+    // CPU breakdown: one line per cluster, with per-core figures if known
+    for cluster in &power.clusters {
+        let mut spans = vec![
+            Span::styled(format!("  {:<3}", cluster.name), Style::default().fg(Color::Cyan)),
+            Span::raw(format!("{:.2}W", cluster.watts)),
+        ];
+        if !cluster.cores.is_empty() {
+            let cores: Vec<String> = cluster.cores.iter().map(|w| format!("{:.1}", w)).collect();
+            spans.push(Span::styled(
+                format!("  {}", cores.join(" ")),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        text.push(Line::from(spans));
+    }
+
+    let mut total_line = vec![
+        Span::styled("Total: ", Style::default().fg(Color::Yellow)),
+        Span::styled(
+            format!("{:.1}W", total),
+            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+        ),
+    ];
+    if power.package_watts > 0.0 {
+        total_line.push(Span::styled(
+            format!("   package {:.1}W", power.package_watts),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if power.system_watts > 0.0 {
+        total_line.push(Span::styled(
+            format!("   system {:.1}W", power.system_watts),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    text.push(Line::from(total_line));
+
     // Split inner area: text on top, sparkline on bottom
+    let text_height = (text.len() as u16).min(inner.height - 1);
     let chunks = Layout::vertical([
-        Constraint::Length(2),
+        Constraint::Length(text_height),
         Constraint::Min(1),
     ])
     .split(inner);
 
-    let total = power.cpu_watts + power.gpu_watts;
-    let text = vec![
-        Line::from(vec![
-            Span::styled("CPU: ", Style::default().fg(Color::Cyan)),
-            Span::raw(format!("{:.1}W", power.cpu_watts)),
-            Span::raw("   "),
-            Span::styled("GPU: ", Style::default().fg(Color::LightMagenta)),
-            Span::raw(format!("{:.1}W", power.gpu_watts)),
-        ]),
-        Line::from(vec![
-            Span::styled("Total: ", Style::default().fg(Color::Yellow)),
-            Span::styled(
-                format!("{:.1}W", total),
-                Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
-            ),
-        ]),
-    ];
-
     let paragraph = Paragraph::new(text);
     frame.render_widget(paragraph, chunks[0]);
 
@@ -55,3 +92,19 @@ pub fn render(
 
     frame.render_widget(sparkline, chunks[1]);
 }
+
+/// "Label: 1.2W" spans for each non-zero domain; CPU and GPU always show.
+fn parts(domains: &[(&'static str, Color, f64)]) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    for &(label, color, watts) in domains {
+        if watts <= 0.0 && label != "CPU" && label != "GPU" {
+            continue;
+        }
+        if !spans.is_empty() {
+            spans.push(Span::raw("   "));
+        }
+        spans.push(Span::styled(format!("{}: ", label), Style::default().fg(color)));
+        spans.push(Span::raw(format!("{:.1}W", watts)));
+    }
+    spans
+}
@@ -0,0 +1,129 @@
+use super::format_size;
+use crate::sources::process::ProcessInfo;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Row, Table};
+use std::cmp::Reverse;
+
+/// Column the process table is sorted by, cycled with `s`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortBy {
+    #[default]
+    Cpu,
+    Gpu,
+    Energy,
+    Memory,
+    Threads,
+    Pid,
+    Name,
+}
+
+impl SortBy {
+    pub fn next(self) -> Self {
+        match self {
+            SortBy::Cpu => SortBy::Gpu,
+            SortBy::Gpu => SortBy::Energy,
+            SortBy::Energy => SortBy::Memory,
+            SortBy::Memory => SortBy::Threads,
+            SortBy::Threads => SortBy::Pid,
+            SortBy::Pid => SortBy::Name,
+            SortBy::Name => SortBy::Cpu,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortBy::Cpu => "CPU",
+            SortBy::Gpu => "GPU",
+            SortBy::Energy => "energy",
+            SortBy::Memory => "memory",
+            SortBy::Threads => "threads",
+            SortBy::Pid => "PID",
+            SortBy::Name => "name",
+        }
+    }
+}
+
+pub fn render(frame: &mut ratatui::Frame, area: Rect, processes: &[ProcessInfo], sort: SortBy) {
+    // The sample only holds the top processes by each resource, so PID and
+    // name order that subset rather than every process
+    let scope = match sort {
+        SortBy::Pid | SortBy::Name => " (busiest only)",
+        _ => "",
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Processes  by {}{} ", sort.label(), scope))
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let mut sorted: Vec<&ProcessInfo> = processes.iter().collect();
+    match sort {
+        SortBy::Cpu => sorted.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent)),
+        SortBy::Gpu => sorted.sort_by(|a, b| b.gpu_percent.total_cmp(&a.gpu_percent)),
+        SortBy::Energy => sorted.sort_by(|a, b| b.energy_watts.total_cmp(&a.energy_watts)),
+        SortBy::Memory => sorted.sort_by_key(|p| Reverse(p.resident_bytes)),
+        SortBy::Threads => sorted.sort_by_key(|p| Reverse(p.threads)),
+        SortBy::Pid => sorted.sort_by_key(|p| p.pid),
+        SortBy::Name => sorted.sort_by_cached_key(|p| p.name.to_lowercase()),
+    }
+
+    // Mark the sort column in the header
+    let heading = |name: &'static str, column: SortBy| {
+        let style = Style::default().fg(Color::DarkGray);
+        if column == sort {
+            Span::styled(format!("{}▼", name), style.add_modifier(Modifier::BOLD))
+        } else {
+            Span::styled(name, style)
+        }
+    };
+    let header = Row::new(vec![
+        Line::from(heading("PID", SortBy::Pid)).right_aligned(),
+        Line::from(heading("Name", SortBy::Name)),
+        Line::from(heading("CPU%", SortBy::Cpu)).right_aligned(),
+        Line::from(heading("GPU%", SortBy::Gpu)).right_aligned(),
+        Line::from(heading("Energy", SortBy::Energy)).right_aligned(),
+        Line::from(heading("Memory", SortBy::Memory)).right_aligned(),
+        Line::from(heading("Threads", SortBy::Threads)).right_aligned(),
+    ]);
+
+    let rows: Vec<Row> = sorted
+        .iter()
+        .take(area.height as usize)
+        .map(|p| {
+            let cpu_color = if p.cpu_percent >= 80.0 {
+                Color::Red
+            } else if p.cpu_percent >= 20.0 {
+                Color::Yellow
+            } else {
+                Color::Reset
+            };
+            Row::new(vec![
+                Line::from(p.pid.to_string()).right_aligned(),
+                Line::from(p.name.clone()),
+                Line::from(format!("{:.1}", p.cpu_percent))
+                    .right_aligned()
+                    .style(Style::default().fg(cpu_color)),
+                Line::from(format!("{:.1}", p.gpu_percent)).right_aligned(),
+                Line::from(format!("{:.2}W", p.energy_watts)).right_aligned(),
+                Line::from(format_size(p.resident_bytes as f64)).right_aligned(),
+                Line::from(p.threads.to_string()).right_aligned(),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(7),
+            Constraint::Fill(1),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(9),
+            Constraint::Length(8),
+        ],
+    )
+    .header(header)
+    .block(block);
+
+    frame.render_widget(table, area);
+}
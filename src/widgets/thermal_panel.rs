@@ -0,0 +1,94 @@
+use super::{pressure_color, History};
+use crate::sources::smc::ThermalMetrics;
+use crate::sources::thermal_pressure::PressureChange;
+use std::collections::VecDeque;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline};
+
+pub fn render(
+    frame: &mut ratatui::Frame,
+    area: Rect,
+    thermal: &ThermalMetrics,
+    pressure_log: &VecDeque<PressureChange>,
+    history: &History,
+) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Thermal ")
+        .border_style(Style::default().fg(Color::Red));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if inner.height < 2 {
+        return;
+    }
+
+    let label = Style::default().fg(Color::DarkGray);
+    let mut text = Vec::new();
+
+    let mut temps = Vec::new();
+    for (name, temp) in [("CPU", thermal.cpu_temp_c), ("GPU", thermal.gpu_temp_c)] {
+        if temp > 0.0 {
+            temps.push(Span::styled(format!("{} ", name), label));
+            temps.push(Span::styled(
+                format!("{:.0}°C   ", temp),
+                Style::default().fg(temp_color(temp)),
+            ));
+        }
+    }
+    if temps.is_empty() {
+        temps.push(Span::styled("No temperature sensors", label));
+    }
+    text.push(Line::from(temps));
+
+    for (i, fan) in thermal.fans.iter().enumerate() {
+        let mut spans = vec![
+            Span::styled(format!("Fan {} ", i + 1), label),
+            Span::raw(format!("{:.0} rpm", fan.rpm)),
+        ];
+        if fan.max_rpm > 0.0 {
+            spans.push(Span::styled(format!("  / {:.0}", fan.max_rpm), label));
+        }
+        text.push(Line::from(spans));
+    }
+
+    // Most recent pressure changes first
+    for change in pressure_log.iter().rev() {
+        text.push(Line::from(vec![
+            Span::styled(format!("{} ", change.time), label),
+            Span::styled(
+                change.to.label().to_string(),
+                Style::default().fg(pressure_color(change.to)),
+            ),
+            Span::styled(format!(" GPU {:.0}MHz", change.gpu_freq_mhz), label),
+        ]));
+    }
+
+    // Split inner area: text on top, CPU temperature graph on bottom
+    let text_height = (text.len() as u16).min(inner.height - 1);
+    let chunks = Layout::vertical([
+        Constraint::Length(text_height),
+        Constraint::Min(1),
+    ])
+    .split(inner);
+
+    frame.render_widget(Paragraph::new(text), chunks[0]);
+
+    let sparkline = Sparkline::default()
+        .data(history.data())
+        .max(110)
+        .style(Style::default().fg(Color::LightRed));
+
+    frame.render_widget(sparkline, chunks[1]);
+}
+
+fn temp_color(temp: f64) -> Color {
+    if temp >= 90.0 {
+        Color::Red
+    } else if temp >= 70.0 {
+        Color::Yellow
+    } else {
+        Color::Green
+    }
+}